@@ -1,23 +1,51 @@
 use std::{path::Path, fs, time::{SystemTime, UNIX_EPOCH, SystemTimeError}};
 use serde::Deserialize;
 
+use crate::tasks::{persistence::StoreFormat, GitSync, Goal, Retention};
+
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub path: Option<String>, 
+    #[serde(default)]
     pub last_updated: u64,
+    /// Default `--template` used to render tasks in text output
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Completion targets shown by `tasks goals`
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    /// Tasks file to use instead of `tasks.yaml`, relative to the data directory
+    #[serde(default)]
+    pub data_file: Option<String>,
+    /// Format of the tasks file when its extension doesn't say
+    #[serde(default)]
+    pub storage: Option<StoreFormat>,
+    /// How many copies of the tasks file to keep in `backups/`
+    #[serde(default)]
+    pub backups: Retention,
+    /// Commit the tasks file to git after each change, and sync with a remote
+    #[serde(default)]
+    pub git: Option<GitSync>,
 } 
 
 impl Config {
     pub fn new() -> Self {
         Self {
             path: None, 
-            last_updated: 0
+            last_updated: 0,
+            template: None,
+            goals: Vec::new(),
+            data_file: None,
+            storage: None,
+            backups: Retention::default(),
+            git: None,
         }
     } 
 
-    pub fn load(&mut self, path: &str) -> Result<Config, ConfigError> {
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
         // Check if file exists
-        if !Path::new(path).exists() {
+        if !path.exists() {
             return Err(ConfigError::InvalidPath)
         } 
 
@@ -33,8 +61,8 @@ impl Config {
             .as_secs();
 
         let new_config = Config {
-            path: config.path,
             last_updated: now,
+            ..config
         };
 
         Ok(new_config)
@@ -104,6 +132,8 @@ mod tests {
         // Assert default fields 
         assert_eq!(config.path, None);
         assert_eq!(config.last_updated, 0);
+        assert_eq!(config.template, None);
+        assert!(config.goals.is_empty());
     } 
 
     #[test]
@@ -144,6 +174,59 @@ mod tests {
         let _ = std::fs::remove_file(tmp_file_path);
     } 
 
+    #[test]
+    fn test_load_config_template() {
+        let mut config = Config::new();
+
+        let tmp_file = env::temp_dir().join("test_load_config_template.yaml");
+        let _ = std::fs::write(&tmp_file, "path: ~\ntemplate: \"{id:>4} {name}\"\n");
+
+        let config = config.load(tmp_file.to_str().unwrap()).unwrap();
+
+        assert_eq!(config.template, Some("{id:>4} {name}".to_string()));
+        assert!(config.last_updated > 0);
+    } 
+
+    #[test]
+    fn test_load_config_goals() {
+        let mut config = Config::new();
+
+        let tmp_file = env::temp_dir().join("test_load_config_goals.yaml");
+        let _ = std::fs::write(&tmp_file, "path: ~\ngoals:\n  - target: 15\n    period: week\n");
+
+        let config = config.load(tmp_file.to_str().unwrap()).unwrap();
+
+        assert_eq!(config.goals.len(), 1);
+        assert_eq!(config.goals[0].target, 15);
+    } 
+
+    #[test]
+    fn test_load_config_storage() {
+        let mut config = Config::new();
+
+        let tmp_file = env::temp_dir().join("test_load_config_storage.yaml");
+        let _ = std::fs::write(&tmp_file, "path: ~\ndata_file: data/tasks.db\nstorage: json\n");
+
+        let config = config.load(tmp_file.to_str().unwrap()).unwrap();
+
+        assert_eq!(config.data_file, Some("data/tasks.db".to_string()));
+        assert_eq!(config.storage, Some(StoreFormat::Json));
+        assert_eq!(config.backups, Retention::default());
+    } 
+
+    #[test]
+    fn test_load_config_backups() {
+        let mut config = Config::new();
+
+        let tmp_file = env::temp_dir().join("test_load_config_backups.yaml");
+        let _ = std::fs::write(&tmp_file, "path: ~\nbackups:\n  keep: 3\n");
+
+        let config = config.load(tmp_file.to_str().unwrap()).unwrap();
+
+        assert_eq!(config.backups, Retention { keep: 3, daily: 7 });
+        assert_eq!(config.git, None);
+    } 
+
     #[test]
     fn test_load_invalid_path() {
         let mut config = Config::new();
@@ -1,5 +1,7 @@
 #![allow(unused_imports, dead_code, unused_variables)]
 
+use std::io::IsTerminal;
+
 use structopt::StructOpt;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
@@ -8,8 +10,11 @@ mod tasks;
 mod snippets;
 mod timers;
 mod config;
+mod paths;
 
-use tasks::{TaskCommand, persistence};
+use tasks::{TaskCommand, Template, Workspaces, persistence, store::{self, TaskStore}, output::{OutputFormat, OutputOptions}};
+use config::Config;
+use paths::Paths;
 use crate::tasks::cli;
 
 #[cfg(test)]
@@ -17,6 +22,21 @@ mod tests_common;
 
 #[derive(StructOpt)]
 struct Cli {
+    /// Output format: text, json, csv or ndjson for list, show and stats, md or html for export-report
+    #[structopt(long, global = true, default_value = "text")]
+    format: OutputFormat,
+    /// Disable colored output (also honors the NO_COLOR environment variable)
+    #[structopt(long, global = true)]
+    no_color: bool,
+    /// Template for rendering tasks in text output, e.g. "{id:>4} {due:%b %d} {name}"
+    #[structopt(long, global = true)]
+    template: Option<Template>,
+    /// Tasks file to use, ahead of TASK_MANAGER_DATA and `data_file` in the config
+    #[structopt(long, global = true, parse(from_os_str))]
+    data_file: Option<std::path::PathBuf>,
+    /// Workspace to work in instead of the current one, see `tasks workspace`
+    #[structopt(long, global = true)]
+    workspace: Option<String>,
     #[structopt(subcommand)]
     command: Option<AppCommand>
 } 
@@ -25,17 +45,63 @@ struct Cli {
 enum AppCommand {
     #[structopt(name = "tasks")]
     Tasks(TaskCommand),
+    /// Full-screen terminal UI for browsing and editing tasks
+    Tui,
     Snippets,
     Timers,
 }
 
 fn main() {
     let cli = Cli::from_args();
-    let mut tasks = load_or_default();
+    let config_file = paths::config_file();
+    let config = load_config(&config_file);
+    let paths = Paths::resolve(cli.data_file.as_deref(), config.as_ref().and_then(|c| c.data_file.as_deref()));
+    if let Err(e) = paths.create_dirs() {
+        eprintln!("Can't create the directory for {}: {}", paths.data_file.display(), e);
+    }
+    // `tasks paths` lists them itself
+    if !matches!(&cli.command, Some(AppCommand::Tasks(TaskCommand::Paths { .. }))) {
+        for legacy in paths.left_behind() {
+            eprintln!("{} from an older version is no longer read, run `tasks paths --move-legacy` to move it to {}", legacy.found.display(), legacy.moves_to.display());
+        }
+    }
+    let workspaces = workspaces(&paths, config.as_ref());
+    let workspace = cli.workspace.clone().unwrap_or_else(|| workspaces.current());
+    if let Err(e) = workspaces.check_exists(&workspace) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let store = workspaces.open(&workspace);
+    // Held until main returns, covering the load, the command and its save
+    let _lock = match persistence::lock(Some(store.path()), persistence::LOCK_TIMEOUT) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut tasks = match &cli.command {
+        Some(AppCommand::Tasks(subcommand)) if !tasks::cli::needs_tasks(subcommand, store.as_ref()) => tasks::Tasks::new(),
+        _ => load_or_default(store.as_ref()),
+    };
 
     match &cli.command {
         Some(AppCommand::Tasks(subcommand)) => {
-            tasks::cli::run(&mut tasks, subcommand)
+            let ctx = cli::Context {
+                output: output_options(&cli, config.as_ref(), &paths),
+                goals: config.as_ref().map(|c| c.goals.clone()).unwrap_or_default(),
+                store,
+                git: config.as_ref().and_then(|c| c.git.clone()),
+                workspaces,
+                workspace,
+                paths,
+            };
+            tasks::cli::run(&mut tasks, subcommand, &ctx)
+        },
+        Some(AppCommand::Tui) => {
+            if let Err(e) = tasks::tui::run(&mut tasks, store.as_ref()) {
+                eprintln!("Terminal UI failed: {}", e);
+            } 
         },
         Some(AppCommand::Snippets) => {
             snippets::run()
@@ -49,20 +115,70 @@ fn main() {
     } 
 }
 
-fn load_or_default() -> tasks::Tasks {
-    match persistence::load_from_file(None) {
-        Ok(tasks) => tasks,
+fn output_options(cli: &Cli, config: Option<&Config>, paths: &Paths) -> OutputOptions {
+    let is_terminal = std::io::stdout().is_terminal();
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let color = is_terminal && !cli.no_color && !no_color_env;
+    colored::control::set_override(color);
+
+    OutputOptions {
+        format: cli.format,
+        color,
+        max_width: if is_terminal { tasks::terminal_width() } else { None },
+        template: cli.template.clone().or_else(|| config.and_then(|c| config_template(c, paths))),
+        ..Default::default()
+    } 
+} 
+
+// A missing config file is fine, a broken one is worth a warning
+fn load_config(path: &std::path::Path) -> Option<Config> {
+    match Config::new().load(path) {
+        Ok(config) => Some(config),
+        Err(config::ConfigError::InvalidPath) => None,
+        Err(e) => {
+            eprintln!("Ignoring {}: {}", path.display(), e);
+            None
+        }
+    } 
+} 
+
+fn config_template(config: &Config, paths: &Paths) -> Option<Template> {
+    let source = config.template.as_ref()?;
+
+    match Template::parse(source) {
+        Ok(template) => Some(template),
         Err(e) => {
-            eprintln!("No tasks storage found: {}", e);
+            eprintln!("Ignoring template in {}: {}", paths.config_file.display(), e);
+            None
+        }
+    } 
+} 
+
+// The resolved data file is the default workspace, `storage` from the config
+// picks the format of every workspace
+fn workspaces(paths: &Paths, config: Option<&Config>) -> Workspaces {
+    let retention = config.map(|c| c.backups).unwrap_or_default();
+    Workspaces::new(&paths.data_file, config.and_then(|c| c.storage), retention)
+} 
+
+fn load_or_default(store: &dyn TaskStore) -> tasks::Tasks {
+    match store.load() {
+        Ok(tasks) => tasks,
+        Err(tasks::TaskError::NoFile) => {
+            eprintln!("No tasks storage found: {}", tasks::TaskError::NoFile);
             tasks::Tasks::new()
         }
+        // Carrying on would overwrite the file with an empty list
+        Err(e) => {
+            eprintln!("Can't load tasks from {}: {}", store.path().display(), e);
+            std::process::exit(1);
+        }
     } 
 } 
 
 #[cfg(test)]
 mod tests {
     use assert_cmd::Command;
-    use predicates;
 
     use self::tasks::{TaskCommandUpdateArgs, Tasks};
 
@@ -70,7 +186,21 @@ mod tests {
 
     use super::tests_common::test_setup;
 
-    
+    // Points the data and config files into a fresh temp directory so test
+    // runs never touch the real ones
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn command(dir: &std::path::Path) -> Command {
+        let mut cmd = Command::cargo_bin("task-manager").unwrap();
+        cmd.env(paths::DATA_ENV, dir.join("data").join("tasks.yaml"))
+            .env(paths::CONFIG_ENV, dir.join("config.yaml"));
+        cmd
+    }
+
 
     // #[test]
     // fn test_cli_no_command() {
@@ -82,7 +212,8 @@ mod tests {
     fn test_tasks_command() {
         // test_setup::setup();
 
-        let mut cmd = Command::cargo_bin("task-manager").unwrap();
+        let dir = test_dir("main_test_tasks_command");
+        let mut cmd = command(&dir);
 
         cmd.arg("tasks")
             .arg("add")
@@ -96,11 +227,94 @@ mod tests {
         // assert.stdout(predicates::str::contains("Tasks placeholder"));
     } 
 
+    #[test]
+    fn test_paths_command() {
+        let dir = test_dir("main_test_paths_command");
+
+        let mut cmd = command(&dir);
+        cmd.args(["tasks", "paths"]);
+        cmd.assert()
+            .success()
+            .stdout(predicates::str::contains(format!("{} (not found) (from {})", dir.join("data").join("tasks.yaml").display(), paths::DATA_ENV)));
+        assert!(dir.join("data").is_dir());
+
+        let flag = dir.join("flag").join("tasks.json");
+        let mut cmd = command(&dir);
+        cmd.arg("--data-file").arg(&flag).args(["tasks", "add", "Task", "desc", "2023-03-01T12:00:00Z"]);
+        cmd.assert().success();
+        assert!(flag.exists());
+    }
+
+    #[test]
+    fn test_moves_legacy_files() {
+        let dir = test_dir("main_test_moves_legacy_files");
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        std::fs::write(dir.join("data").join("tasks.yaml"), "").unwrap();
+        let moved = dir.join("home/.local/share/task-manager/tasks.yaml");
+        let legacy = || {
+            let mut cmd = Command::cargo_bin("task-manager").unwrap();
+            cmd.current_dir(&dir).env("HOME", dir.join("home")).env_remove("XDG_DATA_HOME").env_remove("XDG_CONFIG_HOME");
+            cmd
+        };
+
+        legacy().args(["tasks", "list"]).assert()
+            .success()
+            .stderr(predicates::str::contains("--move-legacy"));
+        legacy().args(["tasks", "paths"]).assert()
+            .success()
+            .stdout(predicates::str::contains(format!("Not read:     ./data/tasks.yaml, from an older version, `tasks paths --move-legacy` moves it to {}", moved.display())));
+
+        legacy().args(["tasks", "paths", "--move-legacy"]).assert().success();
+        assert!(moved.exists());
+        assert!(!dir.join("data").join("tasks.yaml").exists());
+    }
+
+    // Two data directories syncing the default and a `work` workspace through
+    // a local bare repo
+    #[test]
+    fn test_syncs_workspaces() {
+        use predicates::prelude::PredicateBooleanExt;
+
+        let root = test_dir("main_test_syncs_workspaces");
+        let remote = root.join("remote.git");
+        std::fs::create_dir_all(&remote).unwrap();
+        assert!(std::process::Command::new("git").args(["init", "-q", "--bare"]).arg(&remote).status().unwrap().success());
+        let (a, b) = (root.join("a"), root.join("b"));
+        for dir in [&a, &b] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("config.yaml"), format!("git:\n  remote: {}\n", remote.display())).unwrap();
+        }
+        let run = |dir: &std::path::Path, args: &[&str]| command(dir).args(args).assert().success();
+
+        run(&a, &["tasks", "add", "Home task", "", "2030-01-01T00:00:00Z"]);
+        run(&a, &["tasks", "sync"]);
+        run(&a, &["tasks", "workspace", "create", "work"]);
+        run(&a, &["--workspace", "work", "tasks", "add", "Work task", "", "2030-01-01T00:00:00Z"]);
+        run(&a, &["--workspace", "work", "tasks", "sync"]);
+        run(&a, &["tasks", "sync"]);
+        assert!(!a.join("data").join("workspaces").join(".git").exists());
+
+        run(&b, &["tasks", "sync"]);
+        for dir in [&a, &b] {
+            run(dir, &["tasks", "list"]).stdout(predicates::str::contains("Home task")).stdout(predicates::str::contains("Work task").not());
+            run(dir, &["--workspace", "work", "tasks", "list"]).stdout(predicates::str::contains("Work task"));
+        }
+
+        // Diverged, each side changed a different workspace
+        run(&b, &["--workspace", "work", "tasks", "add", "From B", "", "2030-01-01T00:00:00Z"]);
+        run(&b, &["tasks", "sync"]);
+        run(&a, &["tasks", "add", "From A", "", "2030-01-01T00:00:00Z"]);
+        run(&a, &["tasks", "sync"]);
+        run(&a, &["tasks", "list"]).stdout(predicates::str::contains("From A"));
+        run(&a, &["--workspace", "work", "tasks", "list"]).stdout(predicates::str::contains("From B"));
+    }
+
     #[test]
     fn test_snippets_command() {
         // test_setup::setup();
 
-        let mut cmd = Command::cargo_bin("task-manager").unwrap();
+        let dir = test_dir("main_test_snippets_command");
+        let mut cmd = command(&dir);
         cmd.arg("snippets");
 
         let assert = cmd.assert();
@@ -114,7 +328,8 @@ mod tests {
     fn test_timers_command() {
         // test_setup::setup();
 
-        let mut cmd = Command::cargo_bin("task-manager").unwrap();
+        let dir = test_dir("main_test_timers_command");
+        let mut cmd = command(&dir);
         cmd.arg("timers");
 
         let assert = cmd.assert();
@@ -128,7 +343,8 @@ mod tests {
     fn test_update_command() {
         // test_setup::setup();
 
-        let mut cmd = Command::cargo_bin("task-manager").unwrap();
+        let dir = test_dir("main_test_update_command");
+        let mut cmd = command(&dir);
 
         cmd.arg("tasks")
             .arg("add")
@@ -139,7 +355,7 @@ mod tests {
         cmd.assert()
             .success();
             
-        let mut cmd = Command::cargo_bin("task-manager").unwrap();
+        let mut cmd = command(&dir);
 
         cmd.arg("tasks")
             .arg("update")
@@ -0,0 +1,257 @@
+use std::{env, ffi::OsString, fmt, fs, io, path::{Path, PathBuf}};
+
+/// Overrides the tasks file, below `--data-file`
+pub const DATA_ENV: &str = "TASK_MANAGER_DATA";
+/// Overrides the config file
+pub const CONFIG_ENV: &str = "TASK_MANAGER_CONFIG";
+
+const APP: &str = "task-manager";
+const TASKS_FILE: &str = "tasks.yaml";
+const CONFIG_FILE: &str = "config.yaml";
+// Where releases before the XDG directories kept both files. Still used when
+// neither the XDG variable nor HOME is set.
+const LEGACY_DIR: &str = "./data";
+
+/// Where the tasks file path was taken from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Flag,
+    Env,
+    Config,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Flag => write!(f, "--data-file"),
+            Source::Env => write!(f, "{}", DATA_ENV),
+            Source::Config => write!(f, "data_file in the config"),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// The config and tasks files in use, resolved once at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    pub config_file: PathBuf,
+    pub data_file: PathBuf,
+    pub data_source: Source,
+    /// Where older releases kept the same files, see `left_behind`.
+    pub legacy: Vec<Legacy>,
+}
+
+/// A file at the path an older release would have used, which this one
+/// reads from `moves_to` instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Legacy {
+    pub found: PathBuf,
+    pub moves_to: PathBuf,
+}
+
+impl Legacy {
+    /// Moves the old file to where it's read from now, unless there's already
+    /// a file there.
+    pub fn move_over(&self) -> io::Result<()> {
+        if self.moves_to.exists() {
+            let message = format!("{} already exists, merge the two by hand", self.moves_to.display());
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+        }
+        if let Some(dir) = self.moves_to.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        // Renaming fails across file systems, e.g. onto a separate /home
+        fs::rename(&self.found, &self.moves_to)
+            .or_else(|_| fs::copy(&self.found, &self.moves_to).and_then(|_| fs::remove_file(&self.found)))
+    }
+}
+
+impl Paths {
+    /// Picks the tasks file from, in order: `--data-file`, `TASK_MANAGER_DATA`,
+    /// `data_file` from the config (relative to the data directory) and
+    /// `tasks.yaml` in the data directory.
+    pub fn resolve(flag: Option<&Path>, configured: Option<&str>) -> Paths {
+        resolve_with(flag, configured, &|name| env::var_os(name))
+    }
+
+    /// Creates the directories the files go in, so the first save succeeds.
+    pub fn create_dirs(&self) -> io::Result<()> {
+        for path in [&self.config_file, &self.data_file] {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Old files that exist but are no longer read, so upgrading doesn't
+    /// silently start from an empty task list.
+    pub fn left_behind(&self) -> Vec<&Legacy> {
+        self.legacy.iter().filter(|l| l.found.is_file() && !same_file(&l.found, &l.moves_to)).collect()
+    }
+}
+
+/// `TASK_MANAGER_CONFIG`, otherwise `config.yaml` in `$XDG_CONFIG_HOME/task-manager`.
+pub fn config_file() -> PathBuf {
+    config_file_with(&|name| env::var_os(name))
+}
+
+/// `$XDG_DATA_HOME/task-manager`, by default `~/.local/share/task-manager`.
+pub fn data_dir() -> PathBuf {
+    base_dir(&|name| env::var_os(name), "XDG_DATA_HOME", ".local/share")
+}
+
+/// The tasks file when nothing overrides it.
+pub fn default_data_file() -> PathBuf {
+    data_dir().join(TASKS_FILE)
+}
+
+type Env<'a> = &'a dyn Fn(&str) -> Option<OsString>;
+
+fn resolve_with(flag: Option<&Path>, configured: Option<&str>, env: Env) -> Paths {
+    let config_file = config_file_with(env);
+    let data_dir = base_dir(env, "XDG_DATA_HOME", ".local/share");
+
+    let (data_file, data_source) = if let Some(path) = flag {
+        (path.to_path_buf(), Source::Flag)
+    } else if let Some(path) = var(env, DATA_ENV) {
+        (path, Source::Env)
+    } else if let Some(path) = configured {
+        (data_dir.join(path), Source::Config)
+    } else {
+        (data_dir.join(TASKS_FILE), Source::Default)
+    };
+
+    // Older releases read both files from `./data`, and a relative
+    // `data_file` from the working directory
+    let mut legacy = Vec::new();
+    match (data_source, configured) {
+        (Source::Default, _) => legacy.push(Legacy { found: Path::new(LEGACY_DIR).join(TASKS_FILE), moves_to: data_file.clone() }),
+        (Source::Config, Some(path)) if Path::new(path).is_relative() => legacy.push(Legacy { found: PathBuf::from(path), moves_to: data_file.clone() }),
+        _ => {}
+    }
+    if var(env, CONFIG_ENV).is_none() {
+        legacy.push(Legacy { found: Path::new(LEGACY_DIR).join(CONFIG_FILE), moves_to: config_file.clone() });
+    }
+
+    Paths { config_file, data_file, data_source, legacy }
+}
+
+fn config_file_with(env: Env) -> PathBuf {
+    var(env, CONFIG_ENV).unwrap_or_else(|| base_dir(env, "XDG_CONFIG_HOME", ".config").join(CONFIG_FILE))
+}
+
+// The XDG spec says to ignore relative values and fall back to the default
+fn base_dir(env: Env, xdg: &str, under_home: &str) -> PathBuf {
+    var(env, xdg)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| var(env, "HOME").map(|home| home.join(under_home)))
+        .map(|dir| dir.join(APP))
+        .unwrap_or_else(|| PathBuf::from(LEGACY_DIR))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+// Set and not empty
+fn var(env: Env, name: &str) -> Option<PathBuf> {
+    env(name).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn resolve(flag: Option<&str>, configured: Option<&str>, vars: &[(&str, &str)]) -> Paths {
+        let vars: HashMap<String, OsString> = vars.iter().map(|(k, v)| (k.to_string(), OsString::from(v))).collect();
+        resolve_with(flag.map(Path::new), configured, &|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn defaults_to_xdg_directories() {
+        let paths = resolve(None, None, &[("HOME", "/home/ada")]);
+        assert_eq!(paths.config_file, Path::new("/home/ada/.config/task-manager/config.yaml"));
+        assert_eq!(paths.data_file, Path::new("/home/ada/.local/share/task-manager/tasks.yaml"));
+        assert_eq!(paths.data_source, Source::Default);
+
+        let paths = resolve(None, None, &[("HOME", "/home/ada"), ("XDG_DATA_HOME", "/data"), ("XDG_CONFIG_HOME", "/etc/xdg")]);
+        assert_eq!(paths.config_file, Path::new("/etc/xdg/task-manager/config.yaml"));
+        assert_eq!(paths.data_file, Path::new("/data/task-manager/tasks.yaml"));
+
+        // Relative XDG values are ignored, and without HOME the old location is used
+        let paths = resolve(None, None, &[("HOME", "/home/ada"), ("XDG_DATA_HOME", "data")]);
+        assert_eq!(paths.data_file, Path::new("/home/ada/.local/share/task-manager/tasks.yaml"));
+        assert_eq!(resolve(None, None, &[]).data_file, Path::new("./data/tasks.yaml"));
+    }
+
+    #[test]
+    fn overrides_in_order_of_precedence() {
+        let vars = [("HOME", "/home/ada"), (DATA_ENV, "/env/tasks.json"), (CONFIG_ENV, "/env/config.yaml")];
+
+        let paths = resolve(Some("flag.db"), Some("config.toml"), &vars);
+        assert_eq!((paths.data_file.as_path(), paths.data_source), (Path::new("flag.db"), Source::Flag));
+        assert_eq!(paths.config_file, Path::new("/env/config.yaml"));
+
+        let paths = resolve(None, Some("config.toml"), &vars);
+        assert_eq!((paths.data_file.as_path(), paths.data_source), (Path::new("/env/tasks.json"), Source::Env));
+
+        let paths = resolve(None, Some("config.toml"), &[("HOME", "/home/ada"), (DATA_ENV, "")]);
+        assert_eq!(paths.data_file, Path::new("/home/ada/.local/share/task-manager/config.toml"));
+        assert_eq!(paths.data_source, Source::Config);
+
+        let paths = resolve(None, Some("/srv/tasks.db"), &[("HOME", "/home/ada")]);
+        assert_eq!(paths.data_file, Path::new("/srv/tasks.db"));
+    }
+
+    #[test]
+    fn finds_files_left_by_older_releases() {
+        let old = |name: &str| Path::new(LEGACY_DIR).join(name);
+
+        let paths = resolve(None, None, &[("HOME", "/home/ada")]);
+        let found: Vec<&Path> = paths.legacy.iter().map(|l| l.found.as_path()).collect();
+        assert_eq!(found, vec![old("tasks.yaml"), old("config.yaml")]);
+        assert_eq!(paths.legacy[0].moves_to, paths.data_file);
+
+        let paths = resolve(None, Some("data/tasks.db"), &[("HOME", "/home/ada"), (CONFIG_ENV, "/env/config.yaml")]);
+        assert_eq!(paths.legacy, vec![Legacy { found: PathBuf::from("data/tasks.db"), moves_to: paths.data_file.clone() }]);
+
+        assert!(resolve(None, None, &[("HOME", "/home/ada"), (DATA_ENV, "/env/tasks.yaml"), (CONFIG_ENV, "/env/config.yaml")]).legacy.is_empty());
+        // Without HOME the old directory is still the one in use
+        assert!(resolve(None, None, &[]).left_behind().is_empty());
+    }
+
+    #[test]
+    fn moves_legacy_files_over() {
+        let dir = env::temp_dir().join("paths_moves_legacy_files_over");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("data")).unwrap();
+        fs::write(dir.join("data/tasks.yaml"), "tasks: []").unwrap();
+        let legacy = Legacy { found: dir.join("data/tasks.yaml"), moves_to: dir.join("share/tasks.yaml") };
+        let paths = Paths { config_file: dir.join("config.yaml"), data_file: legacy.moves_to.clone(), data_source: Source::Default, legacy: vec![legacy.clone()] };
+        assert_eq!(paths.left_behind(), vec![&legacy]);
+
+        legacy.move_over().unwrap();
+
+        assert_eq!(fs::read_to_string(&legacy.moves_to).unwrap(), "tasks: []");
+        assert!(paths.left_behind().is_empty());
+        fs::write(&legacy.found, "tasks: []").unwrap();
+        assert_eq!(legacy.move_over().unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn creates_missing_directories() {
+        let dir = env::temp_dir().join("paths_creates_missing_directories");
+        let _ = fs::remove_dir_all(&dir);
+        let paths = Paths { config_file: dir.join("config/config.yaml"), data_file: dir.join("data/tasks.yaml"), data_source: Source::Default, legacy: Vec::new() };
+
+        paths.create_dirs().unwrap();
+
+        assert!(dir.join("config").is_dir());
+        assert!(dir.join("data").is_dir());
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::Write;
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use colored::Colorize;
+
+use super::{dates, Task, TaskError};
+
+/// Lists open tasks under "Overdue", "Today", "Tomorrow" and then one header
+/// per day for the next `days` days, in the time zone `tz`.
+pub fn write_agenda<Tz: TimeZone>(
+    writer: &mut impl Write,
+    tasks: &[Task],
+    now: DateTime<Utc>,
+    tz: &Tz,
+    days: u32,
+    color: bool,
+) -> Result<(), TaskError>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let today = now.with_timezone(tz).date_naive();
+    // Everything from today on when `days` reaches past the last date
+    let end = today.checked_add_signed(Duration::days(days as i64)).unwrap_or(NaiveDate::MAX);
+
+    let mut open: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+    open.sort_by_key(|t| (t.due_date, t.id));
+
+    let overdue: Vec<&Task> = open.iter().copied().filter(|t| t.due_date < now).collect();
+    let upcoming: Vec<&Task> = open
+        .iter()
+        .copied()
+        .filter(|t| t.due_date >= now && t.due_date.with_timezone(tz).date_naive() < end)
+        .collect();
+
+    if overdue.is_empty() && upcoming.is_empty() {
+        writeln!(writer, "Nothing due in the next {} days", days)?;
+        return Ok(());
+    }
+
+    let mut first = true;
+    let mut header = |writer: &mut dyn Write, title: &str| -> Result<(), TaskError> {
+        if !first {
+            writeln!(writer)?;
+        }
+        first = false;
+        if color {
+            writeln!(writer, "{}", title.bold())?;
+        } else {
+            writeln!(writer, "{}", title)?;
+        }
+        Ok(())
+    };
+
+    if !overdue.is_empty() {
+        header(writer, "Overdue")?;
+        for task in &overdue {
+            write_entry(writer, task, now, tz, true, color)?;
+        }
+    }
+
+    let mut current: Option<NaiveDate> = None;
+    for task in &upcoming {
+        let day = task.due_date.with_timezone(tz).date_naive();
+        if current != Some(day) {
+            header(writer, &day_label(day, today))?;
+            current = Some(day);
+        }
+        write_entry(writer, task, now, tz, false, color)?;
+    }
+
+    Ok(())
+}
+
+fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    match (day - today).num_days() {
+        0 => "Today".to_string(),
+        1 => "Tomorrow".to_string(),
+        _ => day.format("%a %b %-d").to_string(),
+    }
+}
+
+fn write_entry<Tz: TimeZone>(
+    writer: &mut impl Write,
+    task: &Task,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    overdue: bool,
+    color: bool,
+) -> Result<(), TaskError>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let time = task.due_date.with_timezone(tz).format("%H:%M").to_string();
+    let when = dates::relative(task.due_date, now);
+    let line = format!("  {}  #{} {} ({})", time, task.id, task.name, when);
+
+    if color && overdue {
+        writeln!(writer, "{}", line.red())?;
+    } else {
+        writeln!(writer, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use crate::tests_common::{task, TaskFixture};
+
+    use super::*;
+
+    fn sample() -> Vec<Task> {
+        vec![
+            task(1, "Late report", "2024-05-08T09:00:00Z"),
+            task(2, "Standup", "2024-05-10T15:00:00Z"),
+            task(3, "Dentist", "2024-05-11T08:00:00Z"),
+            task(4, "Review", "2024-05-16T10:00:00Z"),
+            task(5, "Done already", "2024-05-10T16:00:00Z").done(),
+            task(6, "Far away", "2024-07-01T10:00:00Z"),
+        ]
+    }
+
+    fn render<Tz: TimeZone>(tz: &Tz, days: u32) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let now = "2024-05-10T12:00:00Z".parse().unwrap();
+        let mut writer = Vec::new();
+        write_agenda(&mut writer, &sample(), now, tz, days, false).unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn groups_open_tasks_by_day() {
+        let output = render(&Utc, 14);
+
+        let expected = "\
+Overdue
+  09:00  #1 Late report (2 days late)
+
+Today
+  15:00  #2 Standup (in 3h)
+
+Tomorrow
+  08:00  #3 Dentist (in 20h)
+
+Thu May 16
+  10:00  #4 Review (in 5 days)
+";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn limits_to_requested_days() {
+        let output = render(&Utc, 1);
+
+        assert!(output.contains("Today"));
+        assert!(!output.contains("Tomorrow"));
+        assert!(!output.contains("Review"));
+    }
+
+    #[test]
+    fn includes_everything_ahead_for_huge_day_counts() {
+        let output = render(&Utc, u32::MAX);
+
+        assert!(output.contains("Far away"));
+    }
+
+    #[test]
+    fn uses_the_given_time_zone() {
+        // 08:00 UTC on the 11th is still the 10th in UTC-10
+        let tz = FixedOffset::west_opt(10 * 3600).unwrap();
+        let output = render(&tz, 14);
+
+        assert!(output.contains("Today\n  05:00  #2 Standup (in 3h)\n  22:00  #3 Dentist"));
+    }
+
+    #[test]
+    fn reports_empty_agenda() {
+        let now = "2024-05-10T12:00:00Z".parse().unwrap();
+        let mut writer = Vec::new();
+        write_agenda(&mut writer, &[], now, &Utc, 7, false).unwrap();
+
+        assert_eq!(String::from_utf8(writer).unwrap(), "Nothing due in the next 7 days\n");
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::Deserialize;
+
+use super::TaskError;
+
+const DIR: &str = "backups";
+const STAMP: &str = "%Y%m%d-%H%M%S%.3f";
+
+/// How many backups `prune` keeps. Setting both to 0 turns backups off.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Retention {
+    /// The most recent backups to keep
+    pub keep: usize,
+    /// Days back for which the newest backup of each day is kept as well
+    pub daily: u32,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention { keep: 10, daily: 7 }
+    }
+}
+
+impl Retention {
+    pub fn none() -> Retention {
+        Retention { keep: 0, daily: 0 }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.keep > 0 || self.daily > 0
+    }
+}
+
+/// A copy of the data file in the `backups/` directory next to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    /// File name, what `tasks backup restore` takes
+    pub name: String,
+    pub path: PathBuf,
+    pub taken: DateTime<Utc>,
+    pub size: u64,
+}
+
+/// `backups/` next to the data file.
+pub fn dir(path: &Path) -> PathBuf {
+    path.with_file_name(DIR)
+}
+
+/// Copies the data file to `backups/<stem>-<timestamp>.<ext>` and prunes old
+/// copies. Does nothing when there is no file yet or backups are off.
+pub fn create(path: &Path, retention: &Retention, now: DateTime<Utc>) -> Result<Option<PathBuf>, TaskError> {
+    if !retention.enabled() || !path.exists() {
+        return Ok(None);
+    }
+
+    let dir = dir(path);
+    fs::create_dir_all(&dir)?;
+    let (stem, extension) = name_parts(path);
+    let backup = dir.join(format!("{}-{}{}", stem, now.format(STAMP), extension));
+    fs::copy(path, &backup)?;
+
+    prune(path, retention, now)?;
+    Ok(Some(backup))
+}
+
+/// Backups of the data file at `path`, newest first.
+pub fn list(path: &Path) -> Result<Vec<Backup>, TaskError> {
+    let entries = match fs::read_dir(dir(path)) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let (stem, extension) = name_parts(path);
+    let prefix = format!("{}-", stem);
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Other data files can share the directory
+        let Some(stamp) = name.strip_prefix(&prefix).and_then(|rest| rest.strip_suffix(&extension)) else { continue };
+        let Ok(taken) = NaiveDateTime::parse_from_str(stamp, STAMP) else { continue };
+        backups.push(Backup { name, path: entry.path(), taken: taken.and_utc(), size: entry.metadata()?.len() });
+    }
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.taken));
+    Ok(backups)
+}
+
+/// Deletes the backups `retention` doesn't cover, returning their names.
+pub fn prune(path: &Path, retention: &Retention, now: DateTime<Utc>) -> Result<Vec<String>, TaskError> {
+    let backups = list(path)?;
+    let oldest_daily = now.date_naive() - Duration::days(retention.daily as i64);
+
+    let mut days = HashSet::new();
+    let mut removed = Vec::new();
+    for (index, backup) in backups.iter().enumerate() {
+        let day = backup.taken.date_naive();
+        // Newest first, so the first one seen for a day is its latest
+        let daily = retention.daily > 0 && day > oldest_daily && days.insert(day);
+        if index < retention.keep || daily {
+            continue;
+        }
+        fs::remove_file(&backup.path)?;
+        removed.push(backup.name.clone());
+    }
+
+    Ok(removed)
+}
+
+/// Finds the backup called `name` for the data file at `path`.
+pub fn find(path: &Path, name: &str) -> Result<Backup, TaskError> {
+    list(path)?
+        .into_iter()
+        .find(|b| b.name == name)
+        .ok_or_else(|| TaskError::InvalidInput(format!("no backup called {} in {}", name, dir(path).display())))
+}
+
+// `tasks.yaml` -> ("tasks", ".yaml")
+fn name_parts(path: &Path) -> (String, String) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (stem, extension)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn data_file(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.yaml");
+        fs::write(&path, "version: 2\nnext_id: 1\ntasks: []\n").unwrap();
+        path
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn keeps_timestamped_copies_next_to_the_file() {
+        let path = data_file("backup_keeps_timestamped_copies");
+
+        let backup = create(&path, &Retention::default(), at("2024-05-01T09:00:00.250Z")).unwrap().unwrap();
+
+        assert_eq!(backup, path.with_file_name("backups").join("tasks-20240501-090000.250.yaml"));
+        let backups = list(&path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].taken, at("2024-05-01T09:00:00.250Z"));
+        assert_eq!(find(&path, "tasks-20240501-090000.250.yaml").unwrap().path, backup);
+        assert!(find(&path, "tasks-20240501.yaml").is_err());
+    }
+
+    #[test]
+    fn keeps_recent_and_daily_backups() {
+        let path = data_file("backup_keeps_recent_and_daily");
+        let retention = Retention { keep: 2, daily: 3 };
+
+        // Three a day over five days
+        for day in 1..=5 {
+            for hour in [9, 12, 15] {
+                create(&path, &retention, at(&format!("2024-05-0{}T{}:00:00Z", day, hour))).unwrap();
+            }
+        }
+
+        let names: Vec<String> = list(&path).unwrap().into_iter().map(|b| b.name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "tasks-20240505-150000.000.yaml",
+                "tasks-20240505-120000.000.yaml",
+                "tasks-20240504-150000.000.yaml",
+                "tasks-20240503-150000.000.yaml",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_be_turned_off() {
+        let path = data_file("backup_can_be_turned_off");
+
+        assert_eq!(create(&path, &Retention::none(), Utc::now()).unwrap(), None);
+        assert!(list(&path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use std::{collections::BTreeMap, fmt, io::Write, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::{Task, TaskError, table::truncate};
+
+const GAP: &str = "  ";
+const MIN_COLUMN_WIDTH: usize = 14;
+const MAX_COLUMN_WIDTH: usize = 32;
+// Used when the output is not a terminal
+pub const DEFAULT_WIDTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BoardGroup {
+    #[default]
+    Status,
+    Project,
+    Tag,
+}
+
+impl FromStr for BoardGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "status" => Ok(BoardGroup::Status),
+            "project" => Ok(BoardGroup::Project),
+            "tag" => Ok(BoardGroup::Tag),
+            _ => Err(format!("Unknown board grouping: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for BoardGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardGroup::Status => write!(f, "status"),
+            BoardGroup::Project => write!(f, "project"),
+            BoardGroup::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BoardOptions {
+    pub group: BoardGroup,
+    /// Cards shown per column before collapsing the rest into "+N more"
+    pub max_rows: usize,
+    /// Total width available for the board
+    pub width: usize,
+    pub color: bool,
+}
+
+impl Default for BoardOptions {
+    fn default() -> Self {
+        Self { group: BoardGroup::Status, max_rows: 10, width: DEFAULT_WIDTH, color: false }
+    }
+}
+
+struct BoardColumn<'a> {
+    title: String,
+    tasks: Vec<&'a Task>,
+}
+
+/// Draws tasks as side-by-side columns, one per status, project or tag.
+pub fn write_board(writer: &mut impl Write, tasks: &[Task], now: DateTime<Utc>, options: BoardOptions) -> Result<(), TaskError> {
+    let columns = group(tasks, now, options.group);
+    if columns.is_empty() {
+        writeln!(writer, "No tasks")?;
+        return Ok(());
+    }
+
+    let gaps = GAP.len() * (columns.len() - 1);
+    let width = (options.width.saturating_sub(gaps) / columns.len()).clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH);
+
+    let rendered: Vec<Vec<String>> = columns
+        .iter()
+        .map(|column| render_column(column, width, now, options))
+        .collect();
+
+    let height = rendered.iter().map(Vec::len).max().unwrap_or(0);
+    for line in 0..height {
+        let row: Vec<String> = rendered
+            .iter()
+            .map(|column| column.get(line).cloned().unwrap_or_else(|| " ".repeat(width)))
+            .collect();
+        writeln!(writer, "{}", row.join(GAP).trim_end())?;
+    }
+
+    Ok(())
+}
+
+fn group(tasks: &[Task], now: DateTime<Utc>, group: BoardGroup) -> Vec<BoardColumn<'_>> {
+    match group {
+        BoardGroup::Status => {
+            let mut overdue = Vec::new();
+            let mut open = Vec::new();
+            let mut done = Vec::new();
+            for task in tasks {
+                if task.completed {
+                    done.push(task);
+                } else if task.due_date < now {
+                    overdue.push(task);
+                } else {
+                    open.push(task);
+                }
+            }
+            vec![
+                BoardColumn { title: "Overdue".to_string(), tasks: overdue },
+                BoardColumn { title: "Open".to_string(), tasks: open },
+                BoardColumn { title: "Done".to_string(), tasks: done },
+            ]
+        }
+        BoardGroup::Project => {
+            let mut by_project: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+            let mut none = Vec::new();
+            for task in tasks {
+                match &task.project {
+                    Some(project) => by_project.entry(project).or_default().push(task),
+                    None => none.push(task),
+                }
+            }
+            named_columns(by_project, "No project", none)
+        }
+        BoardGroup::Tag => {
+            // A task with several tags shows up in each of their columns
+            let mut by_tag: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+            let mut none = Vec::new();
+            for task in tasks {
+                if task.tags.is_empty() {
+                    none.push(task);
+                }
+                for tag in &task.tags {
+                    by_tag.entry(tag).or_default().push(task);
+                }
+            }
+            named_columns(by_tag, "Untagged", none)
+        }
+    }
+}
+
+fn named_columns<'a>(groups: BTreeMap<&str, Vec<&'a Task>>, rest_title: &str, rest: Vec<&'a Task>) -> Vec<BoardColumn<'a>> {
+    let mut columns: Vec<BoardColumn> = groups
+        .into_iter()
+        .map(|(title, tasks)| BoardColumn { title: title.to_string(), tasks })
+        .collect();
+    if !rest.is_empty() {
+        columns.push(BoardColumn { title: rest_title.to_string(), tasks: rest });
+    }
+    columns
+}
+
+// Lines are padded to the column width before coloring so escape codes don't
+// shift the columns to their right.
+fn render_column(column: &BoardColumn, width: usize, now: DateTime<Utc>, options: BoardOptions) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let header = pad(&truncate(&format!("{} ({})", column.title, column.tasks.len()), width), width);
+    lines.push(if options.color { header.bold().to_string() } else { header });
+    lines.push("─".repeat(width));
+
+    for task in column.tasks.iter().take(options.max_rows) {
+        for text in wrap(&format!("#{} {}", task.id, task.name), width) {
+            let text = pad(&text, width);
+            let text = if !options.color {
+                text
+            } else if task.completed {
+                text.dimmed().to_string()
+            } else if task.due_date < now {
+                text.red().to_string()
+            } else {
+                text
+            };
+            lines.push(text);
+        }
+    }
+
+    if column.tasks.len() > options.max_rows {
+        lines.push(pad(&format!("+{} more", column.tasks.len() - options.max_rows), width));
+    }
+
+    lines
+}
+
+/// Word-wraps `text` into lines of at most `width` terminal cells, breaking
+/// words that are longer than a whole line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let needed = if current.is_empty() { word.width() } else { current.width() + 1 + word.width() };
+        if needed <= width {
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            if current.width() + c.width().unwrap_or(0) > width {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::{task, TaskFixture};
+
+    use super::*;
+
+    fn sample() -> Vec<Task> {
+        vec![
+            task(1, "Write the quarterly report for finance", "2024-05-01T09:00:00Z").in_project("work").tagged(&["writing"]),
+            task(2, "Pay invoice", "2024-05-20T09:00:00Z").in_project("home"),
+            task(3, "Book flights", "2024-05-02T09:00:00Z").done().tagged(&["travel", "writing"]),
+        ]
+    }
+
+    fn render(tasks: &[Task], options: BoardOptions) -> String {
+        let now = "2024-05-10T12:00:00Z".parse().unwrap();
+        let mut writer = Vec::new();
+        write_board(&mut writer, tasks, now, options).unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn parse_board_group_from_str() {
+        assert!(matches!(BoardGroup::from_str("status"), Ok(BoardGroup::Status)));
+        assert!(matches!(BoardGroup::from_str("project"), Ok(BoardGroup::Project)));
+        assert!(matches!(BoardGroup::from_str("tag"), Ok(BoardGroup::Tag)));
+        assert!(BoardGroup::from_str("owner").is_err());
+    }
+
+    #[test]
+    fn groups_by_status_with_counts() {
+        let output = render(&sample(), BoardOptions::default());
+        let header = output.lines().next().unwrap();
+
+        assert!(header.starts_with("Overdue (1)"));
+        assert!(header.contains("Open (1)"));
+        assert!(header.contains("Done (1)"));
+        // Cards sit under their column header
+        let open_at = header.find("Open").unwrap();
+        let invoice = output.lines().find(|l| l.contains("#2 Pay invoice")).unwrap();
+        assert_eq!(invoice.find("#2"), Some(open_at));
+    }
+
+    #[test]
+    fn groups_by_project_and_tag() {
+        let output = render(&sample(), BoardOptions { group: BoardGroup::Project, ..Default::default() });
+        let header = output.lines().next().unwrap();
+        assert!(header.starts_with("home (1)"));
+        assert!(header.contains("work (1)"));
+        assert!(header.contains("No project (1)"));
+
+        let output = render(&sample(), BoardOptions { group: BoardGroup::Tag, ..Default::default() });
+        let header = output.lines().next().unwrap();
+        assert!(header.starts_with("travel (1)"));
+        assert!(header.contains("writing (2)"));
+        assert!(header.contains("Untagged (1)"));
+    }
+
+    #[test]
+    fn wraps_long_names() {
+        let output = render(&sample(), BoardOptions { width: 40, ..Default::default() });
+
+        assert!(output.contains("#1 Write the"));
+        assert!(!output.contains("#1 Write the quarterly report for finance"));
+        for line in output.lines() {
+            assert!(line.width() <= (MIN_COLUMN_WIDTH + GAP.len()) * 3);
+        }
+    }
+
+    #[test]
+    fn caps_rows_per_column() {
+        let tasks: Vec<Task> = (1..=5)
+            .map(|id| task(id, "Todo", "2024-06-01T09:00:00Z"))
+            .collect();
+
+        let output = render(&tasks, BoardOptions { max_rows: 2, ..Default::default() });
+
+        assert!(output.contains("Open (5)"));
+        assert!(output.contains("#2 Todo"));
+        assert!(!output.contains("#3 Todo"));
+        assert!(output.contains("+3 more"));
+    }
+
+    #[test]
+    fn wrap_breaks_long_words() {
+        assert_eq!(wrap("a bb ccc", 4), vec!["a bb", "ccc"]);
+        assert_eq!(wrap("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(wrap("", 3), vec![""]);
+    }
+}
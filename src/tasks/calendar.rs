@@ -0,0 +1,351 @@
+use std::{collections::HashMap, fmt, io::Write, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use colored::Colorize;
+use unicode_width::UnicodeWidthStr;
+
+use super::{Task, TaskError, table::truncate};
+
+const CELL_WIDTH: usize = 12;
+const TIME_WIDTH: usize = 5;
+// Tasks listed per day before collapsing into "+N more"
+const MAX_NAMES: usize = 3;
+// Hours always shown in the week view, widened to fit earlier or later tasks
+const WORKDAY: (u32, u32) = (8, 18);
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// A calendar month given on the command line as `YYYY-MM`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Month {
+    pub year: i32,
+    pub month: u32,
+}
+
+impl Month {
+    pub fn containing(date: NaiveDate) -> Month {
+        Month { year: date.year(), month: date.month() }
+    }
+
+    fn first_day(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year, self.month, 1).expect("month is validated when parsed")
+    }
+
+    fn last_day(&self) -> NaiveDate {
+        let (year, month) = if self.month == 12 { (self.year + 1, 1) } else { (self.year, self.month + 1) };
+        NaiveDate::from_ymd_opt(year, month, 1).expect("month is validated when parsed") - Duration::days(1)
+    }
+}
+
+impl FromStr for Month {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid month '{}', expected YYYY-MM", s);
+        let (year, month) = s.split_once('-').ok_or_else(err)?;
+        let year: i32 = year.parse().map_err(|_| err())?;
+        let month: u32 = month.parse().map_err(|_| err())?;
+
+        if NaiveDate::from_ymd_opt(year, month, 1).is_none() {
+            return Err(err());
+        }
+
+        Ok(Month { year, month })
+    }
+}
+
+impl fmt::Display for Month {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CalendarOptions {
+    /// List task names in each day instead of a count
+    pub names: bool,
+    pub color: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DayState {
+    Open,
+    Overdue,
+    Completed,
+}
+
+impl DayState {
+    fn of(task: &Task, now: DateTime<Utc>) -> DayState {
+        if task.completed {
+            DayState::Completed
+        } else if task.due_date < now {
+            DayState::Overdue
+        } else {
+            DayState::Open
+        }
+    }
+
+    // Marks keep the states apart when colors are off
+    fn marker(&self) -> &'static str {
+        match self {
+            DayState::Open => "·",
+            DayState::Overdue => "!",
+            DayState::Completed => "✓",
+        }
+    }
+
+    fn paint(&self, text: &str, color: bool) -> String {
+        if !color {
+            return text.to_string();
+        }
+        match self {
+            DayState::Open => text.to_string(),
+            DayState::Overdue => text.red().to_string(),
+            DayState::Completed => text.dimmed().to_string(),
+        }
+    }
+}
+
+/// Draws a Monday-first month grid with the tasks due on each day.
+pub fn write_month<Tz: TimeZone>(
+    writer: &mut impl Write,
+    tasks: &[Task],
+    month: Month,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    options: CalendarOptions,
+) -> Result<(), TaskError> {
+    let today = now.with_timezone(tz).date_naive();
+    let by_day = group_by_day(tasks, tz);
+
+    let first = month.first_day();
+    let last = month.last_day();
+    let grid_width = (CELL_WIDTH + 1) * 7 - 1;
+
+    let title = format!("{:^width$}", first.format("%B %Y").to_string(), width = grid_width);
+    writeln!(writer, "{}", title.trim_end())?;
+    let header: Vec<String> = WEEKDAYS.iter().map(|d| pad(d, CELL_WIDTH)).collect();
+    writeln!(writer, "{}", header.join(" ").trim_end())?;
+    writeln!(writer, "{}", "─".repeat(grid_width))?;
+
+    let mut week_start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    while week_start <= last {
+        let days: Vec<NaiveDate> = (0..7).map(|i| week_start + Duration::days(i)).collect();
+        let cells: Vec<Vec<String>> = days
+            .iter()
+            .map(|day| {
+                if day.month() != month.month {
+                    return Vec::new();
+                }
+                let due = by_day.get(day).map(Vec::as_slice).unwrap_or(&[]);
+                day_cell(*day, due, today, now, options)
+            })
+            .collect();
+
+        let height = cells.iter().map(Vec::len).max().unwrap_or(1).max(2);
+        for line in 0..height {
+            let row: Vec<String> = cells
+                .iter()
+                .map(|cell| cell.get(line).cloned().unwrap_or_else(|| " ".repeat(CELL_WIDTH)))
+                .collect();
+            writeln!(writer, "{}", row.join(" ").trim_end())?;
+        }
+
+        week_start += Duration::days(7);
+    }
+
+    Ok(())
+}
+
+/// Draws the week containing `day` with one row per hour.
+pub fn write_week<Tz: TimeZone>(
+    writer: &mut impl Write,
+    tasks: &[Task],
+    day: NaiveDate,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    options: CalendarOptions,
+) -> Result<(), TaskError> {
+    let today = now.with_timezone(tz).date_naive();
+    let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+    let days: Vec<NaiveDate> = (0..7).map(|i| monday + Duration::days(i)).collect();
+
+    let mut slots: HashMap<(NaiveDate, u32), Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        let local = task.due_date.with_timezone(tz);
+        if days.contains(&local.date_naive()) {
+            slots.entry((local.date_naive(), local.hour())).or_default().push(task);
+        }
+    }
+
+    let first_hour = slots.keys().map(|(_, h)| *h).min().unwrap_or(WORKDAY.0).min(WORKDAY.0);
+    let last_hour = slots.keys().map(|(_, h)| *h).max().unwrap_or(WORKDAY.1).max(WORKDAY.1);
+
+    writeln!(writer, "Week of {}", monday.format("%a %b %-d %Y"))?;
+    let mut header = vec![" ".repeat(TIME_WIDTH)];
+    for day in &days {
+        let label = pad(&day.format("%a %-d").to_string(), CELL_WIDTH);
+        header.push(if options.color && *day == today { label.bold().to_string() } else { label });
+    }
+    writeln!(writer, "{}", header.join(" ").trim_end())?;
+    writeln!(writer, "{}", "─".repeat(TIME_WIDTH + (CELL_WIDTH + 1) * 7))?;
+
+    for hour in first_hour..=last_hour {
+        let mut row = vec![format!("{:02}:00", hour)];
+        for day in &days {
+            let cell = match slots.get(&(*day, hour)) {
+                Some(due) => {
+                    let first = due[0];
+                    let state = DayState::of(first, now);
+                    let text = if due.len() > 1 {
+                        format!("{} {} +{}", state.marker(), first.name, due.len() - 1)
+                    } else {
+                        format!("{} {}", state.marker(), first.name)
+                    };
+                    state.paint(&pad(&truncate(&text, CELL_WIDTH), CELL_WIDTH), options.color)
+                }
+                None => " ".repeat(CELL_WIDTH),
+            };
+            row.push(cell);
+        }
+        writeln!(writer, "{}", row.join(" ").trim_end())?;
+    }
+
+    Ok(())
+}
+
+fn group_by_day<'a, Tz: TimeZone>(tasks: &'a [Task], tz: &Tz) -> HashMap<NaiveDate, Vec<&'a Task>> {
+    let mut by_day: HashMap<NaiveDate, Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        by_day.entry(task.due_date.with_timezone(tz).date_naive()).or_default().push(task);
+    }
+    by_day
+}
+
+// Every line of a cell is padded to CELL_WIDTH before it is colored so the
+// escape codes don't upset the alignment.
+fn day_cell(day: NaiveDate, due: &[&Task], today: NaiveDate, now: DateTime<Utc>, options: CalendarOptions) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let number = if day == today { format!("{:>2} today", day.day()) } else { format!("{:>2}", day.day()) };
+    let number = pad(&number, CELL_WIDTH);
+    lines.push(if options.color && day == today { number.bold().to_string() } else { number });
+
+    if due.is_empty() {
+        return lines;
+    }
+
+    if options.names {
+        for task in due.iter().take(MAX_NAMES) {
+            let state = DayState::of(task, now);
+            let text = truncate(&format!("{} {}", state.marker(), task.name), CELL_WIDTH);
+            lines.push(state.paint(&pad(&text, CELL_WIDTH), options.color));
+        }
+        if due.len() > MAX_NAMES {
+            lines.push(pad(&format!("  +{} more", due.len() - MAX_NAMES), CELL_WIDTH));
+        }
+    } else {
+        let states: Vec<DayState> = due.iter().map(|t| DayState::of(t, now)).collect();
+        // The most urgent state wins the styling of the count
+        let state = if states.contains(&DayState::Overdue) {
+            DayState::Overdue
+        } else if states.iter().all(|s| *s == DayState::Completed) {
+            DayState::Completed
+        } else {
+            DayState::Open
+        };
+        let text = format!("{} {} due", state.marker(), due.len());
+        lines.push(state.paint(&pad(&text, CELL_WIDTH), options.color));
+    }
+
+    lines
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::{task, TaskFixture};
+
+    use super::*;
+
+    fn sample() -> Vec<Task> {
+        vec![
+            task(1, "Report", "2024-05-02T09:30:00Z"),
+            task(2, "Invoice", "2024-05-02T15:00:00Z").done(),
+            task(3, "Standup", "2024-05-20T10:00:00Z"),
+            task(4, "Elsewhere", "2024-06-01T10:00:00Z"),
+        ]
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2024-05-10T12:00:00Z".parse().unwrap()
+    }
+
+    fn month(options: CalendarOptions) -> String {
+        let mut writer = Vec::new();
+        write_month(&mut writer, &sample(), "2024-05".parse().unwrap(), now(), &Utc, options).unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn parse_month_from_str() {
+        assert_eq!(Month::from_str("2024-05"), Ok(Month { year: 2024, month: 5 }));
+        assert!(Month::from_str("2024-13").is_err());
+        assert!(Month::from_str("May").is_err());
+        assert_eq!(Month { year: 2024, month: 5 }.to_string(), "2024-05");
+    }
+
+    #[test]
+    fn month_bounds() {
+        let feb = Month { year: 2024, month: 2 };
+        assert_eq!(feb.last_day(), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        let dec = Month { year: 2023, month: 12 };
+        assert_eq!(dec.last_day(), NaiveDate::from_ymd_opt(2023, 12, 31).unwrap());
+    }
+
+    #[test]
+    fn draws_month_grid_with_counts() {
+        let output = month(CalendarOptions::default());
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines[0].contains("May 2024"));
+        assert!(lines[1].starts_with("Mon"));
+        // May 1st 2024 is a Wednesday, so the first week starts two cells in
+        let wednesday = (CELL_WIDTH + 1) * 2;
+        assert_eq!(&lines[3][wednesday..wednesday + 2], " 1");
+        // One overdue and one completed task on the 2nd
+        assert!(output.contains("! 2 due"));
+        assert!(output.contains("· 1 due"));
+        assert!(output.contains("10 today"));
+        assert!(!output.contains("Elsewhere"));
+        assert!(output.contains("31"));
+    }
+
+    #[test]
+    fn lists_names_with_state_markers() {
+        let output = month(CalendarOptions { names: true, color: false });
+
+        assert!(output.contains("! Report"));
+        assert!(output.contains("✓ Invoice"));
+        assert!(output.contains("· Standup"));
+    }
+
+    #[test]
+    fn draws_week_with_hour_slots() {
+        let mut writer = Vec::new();
+        let day = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        write_week(&mut writer, &sample(), day, now(), &Utc, CalendarOptions::default()).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+
+        assert!(output.starts_with("Week of Mon Apr 29 2024"));
+        let nine = output.lines().find(|l| l.starts_with("09:00")).unwrap();
+        assert!(nine.contains("! Report"));
+        let three = output.lines().find(|l| l.starts_with("15:00")).unwrap();
+        assert!(three.contains("✓ Invoice"));
+        assert!(output.lines().any(|l| l.starts_with("18:00")));
+        assert!(!output.contains("Standup"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use chrono::{NaiveDate, TimeZone};
+use colored::Colorize;
+use serde::Serialize;
+
+use super::{Task, TaskError};
+
+const HEIGHT: usize = 10;
+// Widest a single day's column gets when there are only a few days
+const MAX_BAR_WIDTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    /// Open tasks at the end of each day
+    Burndown,
+    /// Completed tasks so far against the total scope
+    Burnup,
+    /// Tasks completed on each day
+    Completions,
+}
+
+impl FromStr for ChartKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "burndown" => Ok(ChartKind::Burndown),
+            "burnup" => Ok(ChartKind::Burnup),
+            "completions" => Ok(ChartKind::Completions),
+            _ => Err(format!("Unknown chart: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ChartKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartKind::Burndown => write!(f, "burndown"),
+            ChartKind::Burnup => write!(f, "burnup"),
+            ChartKind::Completions => write!(f, "completions"),
+        }
+    }
+}
+
+/// Task counts at the end of one day.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DayPoint {
+    pub date: NaiveDate,
+    /// Tasks created and not yet completed
+    pub open: usize,
+    /// Tasks completed on or before this day
+    pub done: usize,
+    /// Every task created so far, `open + done`
+    pub scope: usize,
+    /// Tasks completed on this day
+    pub completed: usize,
+}
+
+/// Replays creation and completion times into one point per day from `from`
+/// to `to`, with days taken in `tz`.
+///
+/// Tasks from before creation times were recorded count as existing from the
+/// start, and completed tasks without a completion time as done before it.
+pub fn history<Tz: TimeZone>(tasks: &[Task], from: NaiveDate, to: NaiveDate, tz: &Tz) -> Vec<DayPoint> {
+    let created = |task: &Task| task.created_at.map(|at| at.with_timezone(tz).date_naive());
+    let completed = |task: &Task| -> Option<Option<NaiveDate>> {
+        task.completed.then(|| task.completed_at.map(|at| at.with_timezone(tz).date_naive()))
+    };
+
+    from.iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| {
+            let mut point = DayPoint { date: day, open: 0, done: 0, scope: 0, completed: 0 };
+            for task in tasks {
+                if created(task).is_some_and(|created| created > day) {
+                    continue;
+                }
+                point.scope += 1;
+                match completed(task) {
+                    Some(None) => point.done += 1,
+                    Some(Some(at)) if at <= day => {
+                        point.done += 1;
+                        if at == day {
+                            point.completed += 1;
+                        }
+                    }
+                    _ => point.open += 1,
+                }
+            }
+            point
+        })
+        .collect()
+}
+
+/// Draws `points` as a bar chart fitting in `width` terminal cells. When there
+/// are more days than columns, neighbouring days are merged.
+pub fn write_chart(
+    writer: &mut impl Write,
+    points: &[DayPoint],
+    kind: ChartKind,
+    width: usize,
+    color: bool,
+) -> Result<(), TaskError> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        writeln!(writer, "No data")?;
+        return Ok(());
+    };
+
+    let title = match kind {
+        ChartKind::Burndown => "Open tasks",
+        ChartKind::Burnup => "Completed (█) against scope (·)",
+        ChartKind::Completions => "Completions per day",
+    };
+    writeln!(writer, "{}, {} to {}", title, first.date, last.date)?;
+
+    // Nothing plotted can exceed the final scope, so it bounds the label width
+    let label_width = last.scope.max(1).to_string().len();
+    let plot_width = width.saturating_sub(label_width + 1).max(1);
+    let (bars, scope) = columns(points, kind, plot_width);
+    let max = bars.iter().chain(&scope).copied().max().unwrap_or(0).max(1);
+    let bar_width = (plot_width / bars.len()).clamp(1, MAX_BAR_WIDTH);
+    // Leave a gap between bars when there is room for one
+    let filled = if bar_width == 1 { 1 } else { bar_width - 1 };
+
+    let level = |value: usize| (value * HEIGHT + max / 2) / max;
+    for row in (1..=HEIGHT).rev() {
+        let label = if row == HEIGHT { max.to_string() } else { String::new() };
+        let mut line = String::new();
+        for (i, value) in bars.iter().enumerate() {
+            let cell = if level(*value) >= row {
+                "█"
+            } else if scope.get(i).is_some_and(|s| level(*s) == row) {
+                "·"
+            } else {
+                " "
+            };
+            line.push_str(&cell.repeat(filled));
+            line.push_str(&" ".repeat(bar_width - filled));
+        }
+        let line = line.trim_end();
+        let line = if color { line.cyan().to_string() } else { line.to_string() };
+        writeln!(writer, "{:>w$}│{}", label, line, w = label_width)?;
+    }
+
+    let axis_width = bars.len() * bar_width;
+    writeln!(writer, "{:>w$}└{}", 0, "─".repeat(axis_width), w = label_width)?;
+
+    let start = first.date.format("%b %-d").to_string();
+    let end = last.date.format("%b %-d").to_string();
+    let gap = axis_width.saturating_sub(start.len() + end.len()).max(1);
+    writeln!(writer, "{} {}{}{}", " ".repeat(label_width), start, " ".repeat(gap), end)?;
+
+    Ok(())
+}
+
+// Values for each plotted column, plus the scope line for burnups
+fn columns(points: &[DayPoint], kind: ChartKind, plot_width: usize) -> (Vec<usize>, Vec<usize>) {
+    let days_per_column = points.len().div_ceil(plot_width);
+
+    let mut bars = Vec::new();
+    let mut scope = Vec::new();
+    for chunk in points.chunks(days_per_column) {
+        let last = chunk[chunk.len() - 1];
+        bars.push(match kind {
+            ChartKind::Burndown => last.open,
+            ChartKind::Burnup => last.done,
+            ChartKind::Completions => chunk.iter().map(|p| p.completed).sum(),
+        });
+        if kind == ChartKind::Burnup {
+            scope.push(last.scope);
+        }
+    }
+
+    (bars, scope)
+}
+
+/// Writes one CSV row per day with every series, whichever chart was asked for.
+pub fn write_csv(writer: &mut impl Write, points: &[DayPoint]) -> Result<(), TaskError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for point in points {
+        csv_writer.serialize(point)?;
+    }
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::tests_common::{day, task, TaskFixture};
+
+    use super::*;
+
+    fn sample() -> Vec<Task> {
+        let due = "2024-06-01T09:00:00Z";
+        vec![
+            task(1, "Task 1", due).created("2024-05-01T09:00:00Z").done_at("2024-05-02T10:00:00Z"),
+            task(2, "Task 2", due).created("2024-05-01T09:00:00Z").done_at("2024-05-04T10:00:00Z"),
+            task(3, "Task 3", due).created("2024-05-02T09:00:00Z"),
+            task(4, "Task 4", due).created("2024-05-03T09:00:00Z").done_at("2024-05-04T11:00:00Z"),
+        ]
+    }
+
+    #[test]
+    fn parse_chart_kind_from_str() {
+        assert!(matches!(ChartKind::from_str("burndown"), Ok(ChartKind::Burndown)));
+        assert!(matches!(ChartKind::from_str("burnup"), Ok(ChartKind::Burnup)));
+        assert!(matches!(ChartKind::from_str("completions"), Ok(ChartKind::Completions)));
+        assert!(ChartKind::from_str("pie").is_err());
+    }
+
+    #[test]
+    fn replays_history_per_day() {
+        let points = history(&sample(), day("2024-05-01"), day("2024-05-04"), &Utc);
+
+        let open: Vec<usize> = points.iter().map(|p| p.open).collect();
+        let done: Vec<usize> = points.iter().map(|p| p.done).collect();
+        let scope: Vec<usize> = points.iter().map(|p| p.scope).collect();
+        let completed: Vec<usize> = points.iter().map(|p| p.completed).collect();
+        assert_eq!(open, vec![2, 2, 3, 1]);
+        assert_eq!(done, vec![0, 1, 1, 3]);
+        assert_eq!(scope, vec![2, 3, 4, 4]);
+        assert_eq!(completed, vec![0, 1, 0, 2]);
+    }
+
+    #[test]
+    fn counts_tasks_without_timestamps_from_the_start() {
+        let mut tasks = sample();
+        tasks[2].created_at = None;
+        tasks[0].completed_at = None;
+
+        let points = history(&tasks, day("2024-05-01"), day("2024-05-01"), &Utc);
+
+        assert_eq!(points[0].scope, 3);
+        assert_eq!(points[0].done, 1);
+        assert_eq!(points[0].completed, 0);
+    }
+
+    #[test]
+    fn draws_bars_scaled_to_the_maximum() {
+        let points = history(&sample(), day("2024-05-01"), day("2024-05-04"), &Utc);
+
+        let mut writer = Vec::new();
+        write_chart(&mut writer, &points, ChartKind::Burndown, 40, false).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "Open tasks, 2024-05-01 to 2024-05-04");
+        // The tallest bar (3 open tasks) reaches the top row
+        assert_eq!(lines[1], "3│      ██");
+        assert_eq!(lines[10], " │██ ██ ██ ██");
+        assert_eq!(lines[11], "0└────────────");
+        assert!(lines[12].starts_with("  May 1"));
+        assert!(lines[12].ends_with("May 4"));
+    }
+
+    #[test]
+    fn burnup_marks_scope() {
+        let points = history(&sample(), day("2024-05-01"), day("2024-05-01"), &Utc);
+
+        let mut writer = Vec::new();
+        write_chart(&mut writer, &points, ChartKind::Burnup, 40, false).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+
+        // Nothing is done yet, so only the scope line shows at the top
+        assert!(output.lines().nth(1).unwrap().ends_with("│··"));
+    }
+
+    #[test]
+    fn merges_days_to_fit_the_width() {
+        let points = history(&sample(), day("2024-04-01"), day("2024-05-30"), &Utc);
+
+        let mut writer = Vec::new();
+        write_chart(&mut writer, &points, ChartKind::Completions, 30, false).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+
+        // The title is left alone, the plot itself has to fit
+        for line in output.lines().skip(1) {
+            assert!(line.chars().count() <= 30, "line too wide: {}", line);
+        }
+    }
+
+    #[test]
+    fn writes_every_series_as_csv() {
+        let points = history(&sample(), day("2024-05-01"), day("2024-05-02"), &Utc);
+
+        let mut writer = Vec::new();
+        write_csv(&mut writer, &points).unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer).unwrap(),
+            "date,open,done,scope,completed\n2024-05-01,2,0,2,0\n2024-05-02,2,1,3,1\n"
+        );
+    }
+}
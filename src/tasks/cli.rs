@@ -1,33 +1,326 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
 use structopt::StructOpt;
 
-use crate::tasks::{crud::Tasks, update};
+use crate::paths::Paths;
+use crate::tasks::{crud::Tasks, GitSync, Retention, stats, update, Column, Goal, Stats, StatsGroup, TaskError};
 
-use super::{persistence, filtering::{DueFilter, CompletionFilter}};
+use super::{backup, git::Repo, workspace::{self, Workspaces}, persistence::{self, StoreFormat}, store::{self, Passphrase, TaskStore}, agenda, goals, heatmap::{self, Heatmap}, forecast::Forecast, dates::{self, Span}, report::Report, board::{self, BoardGroup, BoardOptions}, chart::{self, ChartKind}, svg, calendar::{self, CalendarOptions, Month}, output::{self, OutputFormat, OutputOptions}, filtering::{self, DueFilter, CompletionFilter, TaskQuery}};
 
 #[derive(StructOpt)]
 pub enum TaskCommand {
-    Add {name: String, description: String, due_date: String},
+    Add {
+        name: String,
+        description: String,
+        due_date: String,
+        #[structopt(long)]
+        project: Option<String>,
+        /// Tag to attach, may be repeated
+        #[structopt(long = "tag")]
+        tags: Vec<String>,
+    },
     List {
         #[structopt(short, long)]
         due: Option<DueFilter>,
         #[structopt(short, long)]
         status: Option<CompletionFilter>,
+        /// Comma separated table columns: id, name, description, due, status
+        #[structopt(long, use_delimiter = true)]
+        columns: Vec<Column>,
+        /// Show the tasks as they were at the end of this day (event log storage only)
+        #[structopt(long)]
+        as_of: Option<NaiveDate>,
+        /// List the tasks of every workspace, under a heading each
+        #[structopt(long)]
+        all_workspaces: bool,
     }, 
     Delete {id: u32},
     Update { id: u32, fields: String },
     Show {id: u32},
     Complete {id: u32},
-    Stats,
+    Stats {
+        #[structopt(short, long)]
+        due: Option<DueFilter>,
+        #[structopt(short, long)]
+        status: Option<CompletionFilter>,
+        /// Only count tasks in this project
+        #[structopt(long)]
+        project: Option<String>,
+        /// Only count tasks with this tag
+        #[structopt(long)]
+        tag: Option<String>,
+        /// Print a stats row per project, tag or due-bucket
+        #[structopt(long)]
+        group_by: Option<StatsGroup>,
+        /// Number of weeks shown in the completions trend
+        #[structopt(long, default_value = "4")]
+        weeks: u32,
+        /// Print a stats row per workspace
+        #[structopt(long)]
+        all_workspaces: bool,
+    },
+    /// Month grid of tasks by due date
+    Calendar {
+        /// Month to show as YYYY-MM, defaults to the current month
+        #[structopt(long)]
+        month: Option<Month>,
+        /// Show a single week with hourly slots instead of a month
+        #[structopt(long)]
+        week: bool,
+        /// Day whose week is shown with --week, defaults to today
+        #[structopt(long)]
+        date: Option<NaiveDate>,
+        /// List task names instead of counts
+        #[structopt(long)]
+        names: bool,
+    },
+    /// Open tasks grouped by day with relative due dates
+    Agenda {
+        /// Number of days ahead to include
+        #[structopt(long, default_value = "14")]
+        days: u32,
+    },
+    /// Markdown or HTML status report, chosen with --format md|html
+    ExportReport {
+        /// How far back to look for completed tasks, e.g. 7d or 2w
+        #[structopt(long, default_value = "7d")]
+        since: Span,
+        /// Write the report to a file instead of stdout
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Burndown, burnup or completions chart from task history
+    Chart {
+        /// burndown (default), burnup or completions
+        kind: Option<ChartKind>,
+        /// How far back the chart goes, e.g. 30d or 8w
+        #[structopt(long, default_value = "30d")]
+        since: Span,
+        /// Write to a file instead: an SVG with every chart for .svg, otherwise the data as CSV
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Estimated finish dates for open tasks from recent completion throughput
+    Forecast {
+        #[structopt(short, long)]
+        due: Option<DueFilter>,
+        /// Only forecast tasks in this project
+        #[structopt(long)]
+        project: Option<String>,
+        /// Only forecast tasks with this tag
+        #[structopt(long)]
+        tag: Option<String>,
+        /// Weeks of completion history to sample
+        #[structopt(long, default_value = "12")]
+        weeks: u32,
+    },
+    /// Contribution-style heatmap of completions per day over the last year
+    Heatmap {
+        /// Only count tasks in this project
+        #[structopt(long)]
+        project: Option<String>,
+        /// Only count tasks with this tag
+        #[structopt(long)]
+        tag: Option<String>,
+        /// Write an SVG with a tooltip per day to this file instead
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Upgrade the tasks file to the current schema, keeping a backup
+    Migrate {
+        /// Only report what would change
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Copy the tasks into a file in another storage format
+    Convert {
+        /// Target format: yaml, json or toml
+        #[structopt(long)]
+        to: StoreFormat,
+        /// Where to write, defaults to the tasks file with the new extension
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Pull, merge and push the tasks file against the configured git remote
+    Sync,
+    /// List or restore the copies kept in backups/ before each save
+    Backup(BackupCommand),
+    /// Fold the event log into a snapshot (event log storage only)
+    Compact {
+        /// Only fold events before this day, keeping their history queryable
+        #[structopt(long)]
+        before: Option<NaiveDate>,
+    },
+    /// Create, list, switch between or delete named task lists
+    Workspace(WorkspaceCommand),
+    /// Move a task to another workspace, where it gets a new id
+    Move {
+        id: u32,
+        /// Workspace to move the task to
+        #[structopt(long)]
+        to: String,
+    },
+    /// Show which config file, tasks file and workspace are in use
+    Paths {
+        /// Move files left in ./data by older versions to where they're read now
+        #[structopt(long)]
+        move_legacy: bool,
+    },
+    /// Encrypt the tasks file with a passphrase
+    Encrypt,
+    /// Store the tasks file as plain text again
+    Decrypt,
+    /// Progress towards the configured goals and daily completion streaks
+    Goals,
+    /// Board of tasks in columns by status, project or tag
+    Board {
+        #[structopt(short, long)]
+        due: Option<DueFilter>,
+        #[structopt(short, long)]
+        status: Option<CompletionFilter>,
+        /// Column grouping: status, project or tag
+        #[structopt(long, default_value = "status")]
+        by: BoardGroup,
+        /// Cards shown per column
+        #[structopt(long, default_value = "10")]
+        max_rows: usize,
+    },
 } 
 
-pub fn run(tasks: &mut Tasks, cmd: &TaskCommand) {
+#[derive(StructOpt)]
+pub enum BackupCommand {
+    /// Backups of the tasks file, newest first
+    List,
+    /// Replace the tasks file with a backup, after checking it loads
+    Restore {
+        /// File name as shown by `backup list`
+        name: String,
+    },
+}
+
+#[derive(StructOpt)]
+pub enum WorkspaceCommand {
+    /// Start an empty workspace
+    Create { name: String },
+    /// Every workspace with its task count, the current one marked with *
+    List,
+    /// Make a workspace the current one for later commands
+    Use { name: String },
+    /// Delete a workspace and its tasks file
+    Delete {
+        name: String,
+        /// Delete it even if it still has tasks
+        #[structopt(long)]
+        force: bool,
+    },
+}
+
+/// Settings shared by every tasks subcommand, resolved from the global flags.
+#[derive(Debug)]
+pub struct Context {
+    pub output: OutputOptions,
+    /// Completion targets from the config file
+    pub goals: Vec<Goal>,
+    /// Where the tasks were loaded from and are saved to
+    pub store: Box<dyn TaskStore>,
+    /// Commit changes to git and sync them, when configured
+    pub git: Option<GitSync>,
+    /// Every workspace, for `workspace`, `move` and `--all-workspaces`
+    pub workspaces: Workspaces,
+    /// The workspace `store` belongs to
+    pub workspace: String,
+    /// The config file and default tasks file, and what picked the latter
+    pub paths: Paths,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context {
+            output: OutputOptions::default(),
+            goals: Vec::new(),
+            store: store::open(None, None, Retention::default()),
+            git: None,
+            workspaces: Workspaces::default(),
+            workspace: workspace::DEFAULT.to_string(),
+            paths: Paths::resolve(None, None),
+        }
+    }
+}
+
+/// Whether `run` needs `tasks` loaded from `store` first. Listings a store can
+/// filter itself and commands working on files or other workspaces don't.
+pub fn needs_tasks(cmd: &TaskCommand, store: &dyn TaskStore) -> bool {
+    match cmd {
+        TaskCommand::List { as_of: Some(_), .. }
+        | TaskCommand::List { all_workspaces: true, .. }
+        | TaskCommand::Stats { all_workspaces: true, .. } => false,
+        TaskCommand::List { .. } | TaskCommand::Stats { .. } => !store.filters(),
+        TaskCommand::Sync
+        | TaskCommand::Backup(_)
+        | TaskCommand::Compact { .. }
+        | TaskCommand::Workspace(_)
+        | TaskCommand::Paths { .. } => false,
+        _ => true,
+    }
+}
+
+// The commands `run` saves after
+fn mutates(cmd: &TaskCommand) -> bool {
+    matches!(
+        cmd,
+        TaskCommand::Add { .. } | TaskCommand::Update { .. } | TaskCommand::Complete { .. } | TaskCommand::Delete { .. } | TaskCommand::Move { .. }
+    )
+}
+
+/// Runs `cmd` and saves the tasks to `ctx.store` if it changed them. Callers should hold
+/// `persistence::lock` from loading `tasks` until this returns.
+pub fn run(tasks: &mut Tasks, cmd: &TaskCommand, ctx: &Context) {
+    let message = commit_message(tasks, cmd);
 
     match cmd {
-        TaskCommand::Add { name, description, due_date } => {
-            tasks.add_task(name.to_string(), description.to_string(), due_date.to_string());
+        TaskCommand::Add { name, description, due_date, project, tags } => {
+            let index = tasks.add_task(name.to_string(), description.to_string(), due_date.to_string());
+            tasks.tasks[index].project = project.clone();
+            tasks.tasks[index].tags = update::parse_tags(&tags.join(" "));
         } 
-        TaskCommand::List { due, status } => {
-            tasks.list_tasks(&mut std::io::stdout(), due, status);
+        TaskCommand::List { due, status, columns, as_of, all_workspaces } => {
+            let mut options = ctx.output.clone();
+            if !columns.is_empty() {
+                options.columns = columns.clone();
+            } 
+            let query = TaskQuery { due: due.as_ref(), status: status.as_ref(), ..Default::default() };
+            if *all_workspaces {
+                let result = match (as_of, ctx.workspaces.load_all()) {
+                    (Some(_), _) => Err(TaskError::InvalidInput("--as-of can't be combined with --all-workspaces".to_string())),
+                    (None, Ok(all)) => {
+                        let listed: Vec<(String, Vec<crate::tasks::Task>)> = all.into_iter().map(|(name, tasks)| (name, query.filter(&tasks.tasks))).collect();
+                        output::write_workspace_tasks(&mut std::io::stdout(), &listed, &options)
+                    }
+                    (None, Err(e)) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("Can't list every workspace: {}", e);
+                }
+                return;
+            }
+            let listed = match as_of {
+                Some(date) => match ctx.store.as_of(end_of_day(*date)) {
+                    Some(Ok(past)) => query.filter(&past.tasks),
+                    Some(Err(e)) => {
+                        eprintln!("Can't rebuild the tasks as of {}: {}", date, e);
+                        return;
+                    }
+                    None => {
+                        eprintln!("{} keeps no history, switch to `storage: events` for --as-of", ctx.store.path().display());
+                        return;
+                    }
+                },
+                None => select(tasks, &query, ctx),
+            };
+            if let Err(e) = output::write_tasks(&mut std::io::stdout(), &listed, &options) {
+                eprintln!("Error writing: {}", e);
+            } 
         } 
         TaskCommand::Delete { id } => {
             tasks.delete_task(*id);
@@ -43,25 +336,444 @@ pub fn run(tasks: &mut Tasks, cmd: &TaskCommand) {
             }
         } 
         TaskCommand::Show{ id } => {
-            let _ = tasks.show_task(*id, &mut std::io::stdout());
+            let _ = tasks.show_task(*id, &mut std::io::stdout(), &ctx.output);
         } 
         TaskCommand::Complete { id } => {
             let _ = tasks.complete_task(*id);
         } 
-        TaskCommand::Stats => {
-            let stats = tasks.stats();
-            println!("{}", stats);
+        TaskCommand::Calendar { month, week, date, names } => {
+            let now = Utc::now();
+            let today = now.with_timezone(&Local).date_naive();
+            let options = CalendarOptions { names: *names, color: ctx.output.color };
+
+            let result = if *week {
+                calendar::write_week(&mut std::io::stdout(), &tasks.tasks, date.unwrap_or(today), now, &Local, options)
+            } else {
+                let month = month.unwrap_or_else(|| Month::containing(today));
+                calendar::write_month(&mut std::io::stdout(), &tasks.tasks, month, now, &Local, options)
+            };
+            if let Err(e) = result {
+                eprintln!("Error writing: {}", e);
+            } 
+        } 
+        TaskCommand::Agenda { days } => {
+            if let Err(e) = agenda::write_agenda(&mut std::io::stdout(), &tasks.tasks, Utc::now(), &Local, *days, ctx.output.color) {
+                eprintln!("Error writing: {}", e);
+            } 
+        } 
+        TaskCommand::ExportReport { since, output } => {
+            let now = Utc::now();
+            let report = Report::new(&tasks.tasks, since.start(now), now);
+
+            let result = match output {
+                Some(path) => {
+                    let mut buffer = Vec::new();
+                    report.write(&mut buffer, ctx.output.format)
+                        .and_then(|()| std::fs::write(path, buffer).map_err(TaskError::from))
+                }
+                None => report.write(&mut std::io::stdout(), ctx.output.format),
+            };
+            if let Err(e) = result {
+                eprintln!("Error writing report: {}", e);
+            } 
+        } 
+        TaskCommand::Chart { kind, since, output } => {
+            let now = Utc::now();
+            let from = since.start(now).with_timezone(&Local).date_naive();
+            let points = chart::history(&tasks.tasks, from, now.with_timezone(&Local).date_naive(), &Local);
+            let kind = kind.unwrap_or(ChartKind::Burndown);
+
+            let result = match (output, ctx.output.format) {
+                (Some(path), _) => {
+                    let mut buffer = Vec::new();
+                    let is_svg = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+                    let written = if is_svg {
+                        let projects = stats::grouped(&tasks.tasks, StatsGroup::Project, now, stats::DEFAULT_WEEKS);
+                        svg::write_dashboard(&mut buffer, &Stats::at(&tasks.tasks, now, stats::DEFAULT_WEEKS), &points, &projects)
+                    } else {
+                        chart::write_csv(&mut buffer, &points)
+                    };
+                    written.and_then(|()| std::fs::write(path, buffer).map_err(TaskError::from))
+                }
+                (None, OutputFormat::Csv) => chart::write_csv(&mut std::io::stdout(), &points),
+                (None, OutputFormat::Text) => {
+                    let width = ctx.output.max_width.unwrap_or(board::DEFAULT_WIDTH);
+                    chart::write_chart(&mut std::io::stdout(), &points, kind, width, ctx.output.color)
+                }
+                (None, format) => Err(TaskError::InvalidInput(format!("--format {} is not supported by chart, use text or csv", format))),
+            };
+            if let Err(e) = result {
+                eprintln!("Error writing chart: {}", e);
+            } 
+        } 
+        TaskCommand::Forecast { due, project, tag, weeks } => {
+            let now = Utc::now();
+            // Throughput comes from the same project or tag, but not only from
+            // tasks matching the due filter
+            let history = filtering::filter_project_tag(&tasks.tasks, project.as_deref(), tag.as_deref());
+            let open = Tasks::filter_tasks(&history, due.as_ref().unwrap_or(&DueFilter::All), &CompletionFilter::Incomplete);
+            let seed = now.timestamp_nanos_opt().unwrap_or_default() as u64;
+
+            match Forecast::new(&open, &history, now, *weeks, seed) {
+                Some(forecast) => {
+                    if let Err(e) = forecast.write(&mut std::io::stdout(), now) {
+                        eprintln!("Error writing: {}", e);
+                    } 
+                }
+                None => println!("Not enough completion history to forecast, complete a few tasks first"),
+            } 
+        } 
+        TaskCommand::Heatmap { project, tag, output } => {
+            let filtered = filtering::filter_project_tag(&tasks.tasks, project.as_deref(), tag.as_deref());
+            let today = Utc::now().with_timezone(&Local).date_naive();
+
+            let result = match output {
+                Some(path) => {
+                    let mut buffer = Vec::new();
+                    Heatmap::new(&filtered, today, &Local, heatmap::WEEKS).write_svg(&mut buffer)
+                        .and_then(|()| std::fs::write(path, buffer).map_err(TaskError::from))
+                }
+                None => {
+                    let weeks = ctx.output.max_width.map_or(heatmap::WEEKS, heatmap::weeks_for_width);
+                    Heatmap::new(&filtered, today, &Local, weeks).write_terminal(&mut std::io::stdout(), ctx.output.color)
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Error writing heatmap: {}", e);
+            } 
+        } 
+        TaskCommand::Migrate { dry_run } => {
+            let path = ctx.store.path().display();
+            let migration = match ctx.store.plan_migration() {
+                Ok(migration) => migration,
+                Err(e) => {
+                    eprintln!("Can't read {}: {}", path, e);
+                    return;
+                }
+            };
+
+            if migration.from == migration.to {
+                println!("{} is already at schema version {}", path, migration.to);
+                return;
+            }
+            println!("{}: schema version {} -> {}", path, migration.from, migration.to);
+            for change in &migration.changes {
+                println!("  {}", change);
+            }
+            if *dry_run {
+                println!("Dry run, nothing was written");
+                return;
+            }
+
+            let backup = persistence::backup_path(ctx.store.path(), migration.from);
+            match ctx.store.save(tasks) {
+                Ok(()) => println!("Migrated, the old file was kept as {}", backup.display()),
+                Err(e) => eprintln!("Migration failed: {}", e),
+            }
+            return;
+        } 
+        TaskCommand::Convert { to, output } => {
+            let path = output.clone().unwrap_or_else(|| store::converted_path(ctx.store.path(), *to));
+            if path.exists() {
+                eprintln!("{} already exists, not overwriting it", path.display());
+                return;
+            }
+
+            let target = store::open(Some(&path), Some(*to), Retention::none());
+            match target.save(tasks) {
+                Ok(()) => {
+                    println!("Wrote {} tasks to {} as {}", tasks.tasks.len(), path.display(), to);
+                    println!("Set `data_file: {}` in the config to use it", path.display());
+                }
+                Err(e) => eprintln!("Conversion failed: {}", e),
+            }
+            return;
+        } 
+        TaskCommand::Sync => {
+            let Some(git) = &ctx.git else {
+                eprintln!("Set up a `git` section with a `remote` in the config to sync");
+                return;
+            };
+            let Some(remote) = &git.remote else {
+                eprintln!("No `remote` in the `git` section of the config");
+                return;
+            };
+            let result = Repo::open(&ctx.workspaces.root(), ctx.store.path(), &git.branch).and_then(|repo| repo.sync(remote, &git.branch));
+            match result {
+                Ok(report) => {
+                    for note in &report.notes {
+                        println!("  {}", note);
+                    }
+                    let pulled = if report.pulled { "pulled from" } else { "nothing to pull from" };
+                    println!("Synced: {} and pushed to {} ({})", pulled, remote, git.branch);
+                }
+                Err(e) => eprintln!("Sync failed: {}", e),
+            }
+            // The file on disk may now hold merged changes
+            return;
+        } 
+        TaskCommand::Backup(BackupCommand::List) => {
+            match backup::list(ctx.store.path()) {
+                Ok(backups) if backups.is_empty() => println!("No backups in {}", backup::dir(ctx.store.path()).display()),
+                Ok(backups) => {
+                    let now = Utc::now();
+                    for b in backups {
+                        println!("{}  {:>8} bytes  {}", b.name, b.size, dates::relative(b.taken, now));
+                    }
+                }
+                Err(e) => eprintln!("Can't list backups: {}", e),
+            }
+            return;
+        } 
+        TaskCommand::Backup(BackupCommand::Restore { name }) => {
+            match ctx.store.restore(name) {
+                Some(Ok(restored)) => println!("Restored {} from {}", ctx.store.path().display(), restored.name),
+                Some(Err(e)) => eprintln!("Restore failed, nothing was changed: {}", e),
+                None => eprintln!("{} storage keeps no backups", ctx.store.path().display()),
+            }
+            // The loaded tasks are stale now, saving them would undo the restore
+            return;
+        } 
+        TaskCommand::Compact { before } => {
+            let before = before.map(start_of_day);
+            match ctx.store.compact(before) {
+                Some(Ok(0)) => println!("Nothing to compact"),
+                Some(Ok(folded)) => println!("Folded {} events into the snapshot", folded),
+                Some(Err(e)) => eprintln!("Compaction failed: {}", e),
+                None => eprintln!("{} isn't an event log, there is nothing to compact", ctx.store.path().display()),
+            }
+            return;
+        } 
+        TaskCommand::Workspace(command) => {
+            run_workspace(command, ctx);
+            return;
+        } 
+        TaskCommand::Move { id, to } => {
+            if *to == ctx.workspace {
+                eprintln!("Task {} is already in {}", id, to);
+                return;
+            }
+            match move_to(tasks, *id, to, ctx) {
+                Ok(new_id) => println!("Moved #{} to {} as #{}", id, to, new_id),
+                Err(e) => {
+                    eprintln!("Can't move task {}: {}", id, e);
+                    return;
+                }
+            }
+        } 
+        TaskCommand::Paths { move_legacy } => {
+            if *move_legacy {
+                for legacy in ctx.paths.left_behind() {
+                    match legacy.move_over() {
+                        Ok(()) => println!("Moved {} to {}", legacy.found.display(), legacy.moves_to.display()),
+                        Err(e) => eprintln!("Can't move {}: {}", legacy.found.display(), e),
+                    }
+                }
+                return;
+            }
+            let found = |path: &std::path::Path| if path.exists() { "" } else { " (not found)" };
+            let config = &ctx.paths.config_file;
+            println!("Config file:  {}{}", config.display(), found(config));
+            println!("Data file:    {}{} (from {})", ctx.paths.data_file.display(), found(&ctx.paths.data_file), ctx.paths.data_source);
+            println!("Workspace:    {}, {}{}", ctx.workspace, ctx.store.path().display(), found(ctx.store.path()));
+            println!("Backups:      {}", backup::dir(ctx.store.path()).display());
+            for legacy in ctx.paths.left_behind() {
+                println!("Not read:     {}, from an older version, `tasks paths --move-legacy` moves it to {}", legacy.found.display(), legacy.moves_to.display());
+            }
+            return;
+        } 
+        TaskCommand::Encrypt => {
+            let path = ctx.store.path();
+            match ctx.store.encrypt(tasks, Passphrase::Ask) {
+                Some(Ok(copies)) => {
+                    println!("Encrypted {} and {} backups of it", path.display(), copies);
+                    let versioned = ctx.git.is_some() || path.parent().is_some_and(|dir| dir.join(".git").exists());
+                    if versioned {
+                        println!("Earlier git commits still hold the tasks in plain text, rewrite or delete that history if it matters");
+                    }
+                }
+                Some(Err(e)) => eprintln!("Encryption failed, nothing was changed: {}", e),
+                None => eprintln!("{} is already encrypted or isn't a YAML, JSON or TOML file", path.display()),
+            }
+            return;
+        } 
+        TaskCommand::Decrypt => {
+            match ctx.store.decrypt(tasks) {
+                Some(Ok(())) => println!("Decrypted {}, encrypted backups stay in {}", ctx.store.path().display(), backup::dir(ctx.store.path()).display()),
+                Some(Err(e)) => eprintln!("Decryption failed, nothing was changed: {}", e),
+                None => eprintln!("{} isn't encrypted", ctx.store.path().display()),
+            }
+            return;
+        } 
+        TaskCommand::Goals => {
+            if let Err(e) = goals::write_goals(&mut std::io::stdout(), &tasks.tasks, &ctx.goals, Utc::now(), &Local, ctx.output.color) {
+                eprintln!("Error writing: {}", e);
+            } 
+        } 
+        TaskCommand::Board { due, status, by, max_rows } => {
+            let due_filter = due.as_ref().unwrap_or(&DueFilter::All);
+            let completion_filter = status.as_ref().unwrap_or(&CompletionFilter::All);
+            let filtered = Tasks::filter_tasks(&tasks.tasks, due_filter, completion_filter);
+
+            let options = BoardOptions {
+                group: *by,
+                max_rows: *max_rows,
+                width: ctx.output.max_width.unwrap_or(board::DEFAULT_WIDTH),
+                color: ctx.output.color,
+            };
+            if let Err(e) = board::write_board(&mut std::io::stdout(), &filtered, Utc::now(), options) {
+                eprintln!("Error writing: {}", e);
+            } 
+        } 
+        TaskCommand::Stats { due, status, project, tag, group_by, weeks, all_workspaces } => {
+            let query = TaskQuery { due: due.as_ref(), status: status.as_ref(), project: project.as_deref(), tag: tag.as_deref() };
+            let now = Utc::now();
+            if *all_workspaces {
+                let result = match (group_by, ctx.workspaces.load_all()) {
+                    (Some(_), _) => Err(TaskError::InvalidInput("--group-by can't be combined with --all-workspaces".to_string())),
+                    (None, Ok(all)) => {
+                        let groups: Vec<(String, Stats)> = all.into_iter().map(|(name, tasks)| (name, Stats::at(&query.filter(&tasks.tasks), now, *weeks))).collect();
+                        output::write_grouped_stats(&mut std::io::stdout(), &groups, &ctx.output)
+                    }
+                    (None, Err(e)) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("Can't count every workspace: {}", e);
+                }
+                return;
+            }
+            let filtered = select(tasks, &query, ctx);
+
+            let result = match group_by {
+                Some(group) => output::write_grouped_stats(&mut std::io::stdout(), &stats::grouped(&filtered, *group, now, *weeks), &ctx.output),
+                None => output::write_stats(&mut std::io::stdout(), &Stats::at(&filtered, now, *weeks), &ctx.output),
+            };
+            if let Err(e) = result {
+                eprintln!("Error writing: {}", e);
+            } 
         } 
     } 
 
-    match persistence::save_tasks(tasks, None) {
+    // Read-only commands may have run without loading `tasks` at all
+    if !mutates(cmd) {
+        return;
+    }
+    match ctx.store.save(tasks) {
         Ok(()) => (), 
         Err(e) => {
             eprint!("Failed to save tasks: {}", e);
+            return;
         } 
     } 
+
+    if let (Some(git), Some(message)) = (&ctx.git, message) {
+        let result = Repo::open(&ctx.workspaces.root(), ctx.store.path(), &git.branch).and_then(|repo| {
+            // Both workspaces a task moved between
+            if let TaskCommand::Move { to, .. } = cmd {
+                repo.stage(&ctx.workspaces.path(to))?;
+            }
+            repo.commit(&message)
+        });
+        if let Err(e) = result {
+            eprintln!("Saved, but couldn't commit to git: {}", e);
+        }
+    }
+} 
+
+// Describes a mutating command for the git log, e.g. "complete #12: Write
+// report". Taken before the command runs so deleted tasks still have a name.
+fn commit_message(tasks: &Tasks, cmd: &TaskCommand) -> Option<String> {
+    let name = |id: &u32| tasks.tasks.iter().find(|t| t.id == *id).map_or(String::new(), |t| t.name.clone());
+    match cmd {
+        TaskCommand::Add { name, .. } => Some(format!("add #{}: {}", tasks.next_id, name)),
+        TaskCommand::Update { id, .. } => Some(format!("update #{}: {}", id, name(id))),
+        TaskCommand::Complete { id } => Some(format!("complete #{}: {}", id, name(id))),
+        TaskCommand::Delete { id } => Some(format!("delete #{}: {}", id, name(id))),
+        TaskCommand::Move { id, to } => Some(format!("move #{} to {}: {}", id, to, name(id))),
+        _ => None,
+    }
 } 
 
+fn run_workspace(command: &WorkspaceCommand, ctx: &Context) {
+    let workspaces = &ctx.workspaces;
+    match command {
+        WorkspaceCommand::Create { name } => match workspaces.create(name) {
+            Ok(path) => println!("Created workspace {} in {}, switch to it with `tasks workspace use {}`", name, path.display(), name),
+            Err(e) => eprintln!("Can't create workspace {}: {}", name, e),
+        },
+        WorkspaceCommand::List => {
+            let names = match workspaces.list() {
+                Ok(names) => names,
+                Err(e) => {
+                    eprintln!("Can't list workspaces: {}", e);
+                    return;
+                }
+            };
+            for name in names {
+                let marker = if name == ctx.workspace { "*" } else { " " };
+                let count = match workspaces.open(&name).load() {
+                    Ok(tasks) => format!("{} tasks", tasks.tasks.len()),
+                    Err(TaskError::NoFile) => "0 tasks".to_string(),
+                    Err(e) => format!("unreadable: {}", e),
+                };
+                println!("{} {:<16} {}", marker, name, count);
+            }
+        }
+        WorkspaceCommand::Use { name } => match workspaces.switch(name) {
+            Ok(()) => println!("Now using workspace {}", name),
+            Err(e) => eprintln!("Can't switch to {}: {}", name, e),
+        },
+        WorkspaceCommand::Delete { name, force } => match workspaces.delete(name, *force) {
+            Ok(0) => println!("Deleted workspace {}", name),
+            Ok(count) => println!("Deleted workspace {} and its {} tasks", name, count),
+            Err(e) => eprintln!("Can't delete workspace {}: {}", name, e),
+        },
+    }
+}
+
+// Saves the task into the target workspace under its own lock. `tasks` is only
+// saved by the caller on success, so a failure leaves both workspaces as they were.
+fn move_to(tasks: &mut Tasks, id: u32, to: &str, ctx: &Context) -> Result<u32, TaskError> {
+    ctx.workspaces.check_exists(to)?;
+    let target = ctx.workspaces.open(to);
+    let _lock = persistence::lock(Some(target.path()), persistence::LOCK_TIMEOUT)?;
+    let mut moved = match target.load() {
+        Ok(moved) => moved,
+        Err(TaskError::NoFile) => Tasks::new(),
+        Err(e) => return Err(e),
+    };
+
+    let new_id = workspace::move_task(tasks, &mut moved, id)?;
+    target.save(&moved)?;
+    Ok(new_id)
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local.from_local_datetime(&midnight).earliest().map_or(midnight.and_utc(), |at| at.with_timezone(&Utc))
+}
+
+fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+    start_of_day(date + chrono::Duration::days(1)) - chrono::Duration::nanoseconds(1)
+}
+
+// Lets a store that can filter itself answer from its indexes, otherwise
+// filters the loaded tasks
+fn select(tasks: &Tasks, query: &TaskQuery, ctx: &Context) -> Vec<crate::tasks::Task> {
+    match ctx.store.query(query) {
+        Some(Ok(found)) => found,
+        Some(Err(TaskError::NoFile)) | None => query.filter(&tasks.tasks),
+        Some(Err(e)) => {
+            eprintln!("Query failed, filtering in memory instead: {}", e);
+            // `tasks` is left empty for stores that filter themselves
+            match ctx.store.load() {
+                Ok(loaded) => query.filter(&loaded.tasks),
+                Err(_) => query.filter(&tasks.tasks),
+            }
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests{
@@ -71,6 +783,15 @@ mod tests{
 
     use super::*;
 
+    // Keeps the saves at the end of `run` out of the real data directory
+    fn context(name: &str) -> Context {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let workspaces = Workspaces::new(dir.join("tasks.yaml"), None, Retention::none());
+        Context { store: workspaces.open(workspace::DEFAULT), workspaces, ..Default::default() }
+    }
+
     #[test]
     fn test_add_command_parsing() {
         let mut tasks = Tasks::new();
@@ -78,10 +799,12 @@ mod tests{
         let cmd = TaskCommand::Add {
             name: "Task 1".to_string(),
             description: "Description 1".to_string(), 
-            due_date: "2023-03-01T12:00:00Z".to_string()
+            due_date: "2023-03-01T12:00:00Z".to_string(),
+            project: None,
+            tags: vec![],
         };
 
-        run(&mut tasks, &cmd);
+        run(&mut tasks, &cmd, &context("cli_test_add_command_parsing"));
         
         assert_eq!(tasks.tasks.len(), 1);
         assert_eq!(tasks.tasks[0].name, "Task 1");
@@ -89,16 +812,29 @@ mod tests{
         assert_eq!(tasks.tasks[0].due_date.to_string(), "2023-03-01 12:00:00 UTC");
     }
 
+    #[test]
+    fn test_needs_tasks_only_when_the_store_cant_filter() {
+        let dir = std::env::temp_dir().join("cli_test_needs_tasks");
+        let file = store::open(Some(&dir.join("tasks.yaml")), None, Retention::none());
+        let sqlite = store::open(Some(&dir.join("tasks.db")), Some(StoreFormat::Sqlite), Retention::none());
+        let list = TaskCommand::List { due: None, status: None, columns: vec![], as_of: None, all_workspaces: false };
+
+        assert!(needs_tasks(&list, file.as_ref()));
+        assert!(!needs_tasks(&list, sqlite.as_ref()));
+        assert!(!needs_tasks(&TaskCommand::Paths { move_legacy: false }, file.as_ref()));
+        assert!(needs_tasks(&TaskCommand::Complete { id: 1 }, sqlite.as_ref()));
+    }
+
     #[test]
     fn test_list_command() {
         let mut tasks = Tasks::new();
         tasks.add_task("Task 1".to_string(), "".to_string(), "2023-03-01T12:00:00Z".to_string());
 
-        let cmd = TaskCommand::List { due: None, status: None };
+        let cmd = TaskCommand::List { due: None, status: None, columns: vec![], as_of: None, all_workspaces: false };
         
         let mut writer = Vec::new();
-        run(&mut tasks, &cmd);
-        tasks.list_tasks(&mut writer, &None, &None);
+        run(&mut tasks, &cmd, &context("cli_test_list_command"));
+        tasks.list_tasks(&mut writer, &None, &None, &OutputOptions::default());
 
         let output = String::from_utf8(writer).unwrap();
         
@@ -111,7 +847,7 @@ mod tests{
         tasks.add_task("Task 1".to_string(), "".to_string(), "2023-03-01T12:00:00Z".to_string());
 
         let cmd = TaskCommand::Delete { id: 1 };
-        run(&mut tasks, &cmd);
+        run(&mut tasks, &cmd, &context("cli_test_delete_command"));
 
         assert_eq!(tasks.tasks.len(), 0);
     } 
@@ -126,7 +862,7 @@ mod tests{
             fields: "name:New Name, description:Update desc, completed:true".to_string(),
         }; 
 
-        run(&mut tasks, &cmd);
+        run(&mut tasks, &cmd, &context("cli_test_update_command"));
 
         assert_eq!(tasks.tasks[0].name, "New Name");
         assert!(tasks.tasks[0].completed);
@@ -140,9 +876,9 @@ mod tests{
 
         let _ = tasks.complete_task(1);
 
-        let cmd = TaskCommand::Stats; 
+        let cmd = TaskCommand::Stats { due: None, status: None, project: None, tag: None, group_by: None, weeks: 4, all_workspaces: false }; 
         let writer = Vec::new();
-        run(&mut tasks, &cmd);
+        run(&mut tasks, &cmd, &context("cli_test_stats_command"));
         tasks.stats();
 
         let output = String::from_utf8(writer).unwrap();
@@ -158,10 +894,12 @@ mod tests{
         let cmd = TaskCommand::Add {
             name: "Task 1".to_string(),
             description: "Description 1".to_string(),
-            due_date: "invalid date".to_string() 
+            due_date: "invalid date".to_string(),
+            project: None,
+            tags: vec![],
         };
 
-        run(&mut tasks, &cmd);
+        run(&mut tasks, &cmd, &context("cli_test_add_command_invalid_due_date"));
     }
 
     #[test]
@@ -173,12 +911,15 @@ mod tests{
 
         let cmd = TaskCommand::List { 
             due: Some(DueFilter::DueToday), 
-            status: None 
+            status: None,
+            columns: vec![],
+            as_of: None,
+            all_workspaces: false,
         };
         
         let mut writer = Vec::new();
-        run(&mut tasks, &cmd);
-        tasks.list_tasks(&mut writer, &Some(DueFilter::DueToday), &None);
+        run(&mut tasks, &cmd, &context("cli_test_list_command_with_due_filter"));
+        tasks.list_tasks(&mut writer, &Some(DueFilter::DueToday), &None, &OutputOptions::default());
 
         let output = String::from_utf8(writer).unwrap();
         println!("{:?}", output);
@@ -192,11 +933,11 @@ mod tests{
 
         tasks.complete_task(1).expect("There was an error updating the task's completion status.");
 
-        let cmd = TaskCommand::List { due: Some(DueFilter::PastDue), status: Some(CompletionFilter::Complete) };
+        let cmd = TaskCommand::List { due: Some(DueFilter::PastDue), status: Some(CompletionFilter::Complete), columns: vec![], as_of: None, all_workspaces: false };
 
         let mut writer = Vec::new();
-        run(&mut tasks, &cmd);
-        tasks.list_tasks(&mut writer, &Some(DueFilter::PastDue), &Some(CompletionFilter::Complete));
+        run(&mut tasks, &cmd, &context("cli_test_list_command_filters"));
+        tasks.list_tasks(&mut writer, &Some(DueFilter::PastDue), &Some(CompletionFilter::Complete), &OutputOptions::default());
 
         let output = String::from_utf8(writer).unwrap();
         assert!(output.contains("Overdue Task 1"));
@@ -210,8 +951,8 @@ mod tests{
         let cmd = TaskCommand::Show { id: 1 };
 
         let mut writer = Vec::new();
-        run(&mut tasks, &cmd);
-        let _ = tasks.show_task(1, &mut writer);
+        run(&mut tasks, &cmd, &context("cli_test_show_command"));
+        let _ = tasks.show_task(1, &mut writer, &OutputOptions::default());
 
         let output = String::from_utf8(writer).unwrap();
         assert!(output.contains("Overdue Task 1"));
@@ -3,7 +3,7 @@ use chrono::{DateTime, Utc};
 
 use crate::tasks::{Task, update};
 
-use super::{Stats, TaskError, persistence, UpdateFields, filtering::{DueFilter, CompletionFilter}, stats};
+use super::{Stats, TaskError, persistence, UpdateFields, filtering::{DueFilter, CompletionFilter}, output::{self, OutputOptions}, stats};
 
 #[cfg(test)]
 mod tests;
@@ -64,18 +64,16 @@ impl Tasks {
         None
     } 
 
-    pub fn list_tasks(&self, mut writer: impl std::io::Write, due: &Option<DueFilter>, status: &Option<CompletionFilter>) {
+    pub fn list_tasks(&self, mut writer: impl std::io::Write, due: &Option<DueFilter>, status: &Option<CompletionFilter>, options: &OutputOptions) {
         let all_tasks = self.get_tasks();
         let due_filter = due.as_ref().unwrap_or(&DueFilter::All);
         let completion_filter = status.as_ref().unwrap_or(&CompletionFilter::All);
 
         let filtered = Tasks::filter_tasks(all_tasks, due_filter, completion_filter);
 
-        for task in filtered {
-           writeln!(writer, "{}", task).unwrap_or_else(|e| {
-               eprintln!("Error writing: {}", e);
-           }); 
-        } 
+        output::write_tasks(&mut writer, &filtered, options).unwrap_or_else(|e| {
+            eprintln!("Error writing: {}", e);
+        }); 
     }
 
     pub fn update_task(&mut self, id: u32, fields: UpdateFields) -> Result<(), TaskError> {
@@ -103,7 +101,15 @@ impl Tasks {
                     name: fields.name.unwrap_or(task.name.clone()),
                     description: fields.description.unwrap_or(task.description.clone()),
                     due_date,
-                    completed: completed_bool
+                    completed: completed_bool,
+                    created_at: task.created_at,
+                    completed_at: match (task.completed, completed_bool) {
+                        (false, true) => Some(Utc::now()),
+                        (_, false) => None,
+                        (true, true) => task.completed_at,
+                    },
+                    project: fields.project.unwrap_or(task.project.clone()),
+                    tags: fields.tags.unwrap_or(task.tags.clone()),
                 }; 
                 
                 // replace in vector 
@@ -117,19 +123,20 @@ impl Tasks {
         Ok(())
     } 
 
-    pub fn show_task(&self, id: u32, writer: &mut impl Write) -> Result<(), TaskError> {
+    pub fn show_task(&self, id: u32, writer: &mut impl Write, options: &OutputOptions) -> Result<(), TaskError> {
         match self.find_task_by_id(id) {
-            Some((_, task)) => {
-                writeln!(writer, "{}", task)?;
-                Ok(())
-            },
+            Some((_, task)) => output::write_task(writer, task, options),
             None => Err(TaskError::NotFound)
         } 
     } 
 
     pub fn complete_task(&mut self, id: u32) -> Result<(), TaskError> {
         if let Some(index) = self.tasks.iter().position(|t| t.id == id) {
-            self.tasks[index].completed = true;
+            let task = &mut self.tasks[index];
+            if !task.completed {
+                task.completed = true;
+                task.completed_at = Some(Utc::now());
+            } 
             Ok(())
         } else {
             Err(TaskError::NotFound)
@@ -1,3 +1,5 @@
+#![allow(clippy::module_inception)]
+
 mod tests {
     use std::io;
     use std::io::Write;
@@ -13,6 +15,7 @@ mod tests {
     use super::*;
     use crate::tasks::crud::Tasks;
     use crate::tasks::filtering::DueFilter;
+    use crate::tasks::output::OutputOptions;
     use crate::tasks::update;
     use update::UpdateFields;
 
@@ -115,7 +118,7 @@ mod tests {
         let mut writer = Vec::new();
 
         // Act 
-        tasks.list_tasks(&mut writer, &None, &None);
+        tasks.list_tasks(&mut writer, &None, &None, &OutputOptions::default());
 
         // Read output 
         let output = String::from_utf8(writer).unwrap();
@@ -133,23 +136,22 @@ mod tests {
        // Arrange
        let mut tasks = Tasks::new();
        let due_date = Utc::now().to_string();
-       let compare_due_date = due_date.clone();
        tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), due_date);
 
        let mut writer = Vec::new();
 
        // Act 
-       tasks.list_tasks(&mut writer, &None, &None);
+       tasks.list_tasks(&mut writer, &None, &None, &OutputOptions::default());
 
        let output = String::from_utf8(writer).unwrap();
 
-       let expected_output = format!("1 - Task 1 - Text for task1 - {}\n", compare_due_date);
+       let lines: Vec<&str> = output.lines().collect();
 
-       // Assert - output contains task
-       assert_eq!(
-           output,
-           expected_output
-           );
+       // Assert - header, rule and one row for the task
+       assert_eq!(lines.len(), 3);
+       assert!(lines[0].starts_with("ID  Name"));
+       assert!(lines[2].contains("Task 1"));
+       assert!(lines[2].contains("Text for task1"));
 
      }
 
@@ -166,13 +168,13 @@ mod tests {
         let mut writer = Vec::new();
 
         // Act
-        tasks.list_tasks(&mut writer, &None, &None);
+        tasks.list_tasks(&mut writer, &None, &None, &OutputOptions::default());
 
         let output = String::from_utf8(writer).unwrap();
 
       // Assert both tasks printed  
-        assert!(output.contains("1 - Task 1"));
-        assert!(output.contains("2 - Task 2"));
+        assert!(output.contains("1  Task 1"));
+        assert!(output.contains("2  Task 2"));
 
     }
 
@@ -184,12 +186,12 @@ mod tests {
         let mut writer = Vec::new();
 
         // Act
-        tasks.list_tasks(&mut writer, &None, &None);
+        tasks.list_tasks(&mut writer, &None, &None, &OutputOptions::default());
 
         let output = String::from_utf8(writer).unwrap();
 
       // Assert task printed  
-        assert!(output.contains("1 - Task 1"));
+        assert!(output.contains("1  Task 1"));
         
         // Delete task and assert the len is 0
         tasks.delete_task(1);
@@ -198,7 +200,7 @@ mod tests {
 
         // Setup output to check writer contains nothing
         let mut writer = Vec::new();
-        tasks.list_tasks(&mut writer, &None, &None);
+        tasks.list_tasks(&mut writer, &None, &None, &OutputOptions::default());
         let output = String::from_utf8(writer).unwrap();
 
         assert!(output.contains(""));
@@ -245,7 +247,7 @@ mod tests {
 
         // Act 
         let mut output = Vec::new();
-        let _ = tasks.show_task(1, &mut output);
+        let _ = tasks.show_task(1, &mut output, &OutputOptions::default());
         
         // Assert 
         let result = String::from_utf8(output).unwrap();
@@ -265,7 +267,34 @@ mod tests {
 
         // Assert 
         let (_, task) = tasks.find_task_by_id(1).unwrap();
-        assert_eq!(task.completed, true);
+        assert!(task.completed);
+        assert!(task.completed_at.is_some());
+    }
+
+    #[test]
+    fn test_complete_task_keeps_first_completion_time() {
+        let mut tasks = Tasks::new();
+        tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), Utc::now().to_string()); 
+
+        tasks.complete_task(1).unwrap();
+        let first = tasks.tasks[0].completed_at;
+        tasks.complete_task(1).unwrap();
+
+        assert_eq!(tasks.tasks[0].completed_at, first);
+    }
+
+    #[test]
+    fn test_update_completed_sets_completion_time() {
+        let mut tasks = Tasks::new();
+        tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), Utc::now().to_string()); 
+
+        let fields = UpdateFields { completed: Some("true".to_string()), ..Default::default() };
+        tasks.update_task(1, fields).unwrap();
+        assert!(tasks.tasks[0].completed_at.is_some());
+
+        let fields = UpdateFields { completed: Some("false".to_string()), ..Default::default() };
+        tasks.update_task(1, fields).unwrap();
+        assert_eq!(tasks.tasks[0].completed_at, None);
     }
 
     // Testing Update_tasks
@@ -288,6 +317,7 @@ mod tests {
             description: Some("New Desc".to_string()),
             due_date: Some(new_due.to_string()),
             completed: None,
+            ..Default::default()
         };
 
         // Execute update 
@@ -0,0 +1,123 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// A length of time given on the command line, e.g. `7d`, `2w` or `12h`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span(pub Duration);
+
+impl Span {
+    /// The start of the span ending at `now`, the earliest representable
+    /// time for spans reaching back further.
+    pub fn start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now.checked_sub_signed(self.0).unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+}
+
+impl FromStr for Span {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid span '{}', expected e.g. 12h, 7d or 4w", s);
+        let unit = s.chars().last().ok_or_else(err)?;
+        let amount: i64 = s[..s.len() - unit.len_utf8()].parse().map_err(|_| err())?;
+        if amount <= 0 {
+            return Err(err());
+        }
+
+        let unit_seconds = match unit {
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(err()),
+        };
+
+        // `Duration::days` and the like panic out of range, and this chrono
+        // has no `try_days` yet
+        amount
+            .checked_mul(unit_seconds)
+            .filter(|seconds| *seconds <= Duration::max_value().num_seconds())
+            .map(|seconds| Span(Duration::seconds(seconds)))
+            .ok_or_else(|| format!("Span '{}' is too long", s))
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hours = self.0.num_hours();
+        if hours % (24 * 7) == 0 {
+            write!(f, "{}w", hours / (24 * 7))
+        } else if hours % 24 == 0 {
+            write!(f, "{}d", hours / 24)
+        } else {
+            write!(f, "{}h", hours)
+        }
+    }
+}
+
+/// Describes `due` relative to `now`, e.g. "in 3h", "in 2 days" or "2 days late".
+pub fn relative(due: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let diff = due - now;
+    let late = diff < chrono::Duration::zero();
+    let diff = if late { -diff } else { diff };
+
+    let amount = if diff.num_minutes() < 1 {
+        return if late { "just now".to_string() } else { "now".to_string() };
+    } else if diff.num_hours() < 1 {
+        format!("{}m", diff.num_minutes())
+    } else if diff.num_days() < 1 {
+        format!("{}h", diff.num_hours())
+    } else if diff.num_days() == 1 {
+        "1 day".to_string()
+    } else {
+        format!("{} days", diff.num_days())
+    };
+
+    if late {
+        format!("{} late", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_future_dates() {
+        let now = Utc::now();
+        assert_eq!(relative(now + Duration::seconds(20), now), "now");
+        assert_eq!(relative(now + Duration::minutes(45), now), "in 45m");
+        assert_eq!(relative(now + Duration::hours(3) + Duration::minutes(10), now), "in 3h");
+        assert_eq!(relative(now + Duration::hours(30), now), "in 1 day");
+        assert_eq!(relative(now + Duration::days(4), now), "in 4 days");
+    }
+
+    #[test]
+    fn parse_span_from_str() {
+        assert_eq!(Span::from_str("12h"), Ok(Span(Duration::hours(12))));
+        assert_eq!(Span::from_str("7d"), Ok(Span(Duration::days(7))));
+        assert_eq!(Span::from_str("2w"), Ok(Span(Duration::weeks(2))));
+        assert!(Span::from_str("0d").is_err());
+        assert!(Span::from_str("7").is_err());
+        assert!(Span::from_str("d").is_err());
+        assert!(Span::from_str("").is_err());
+        assert_eq!(Span::from_str("99999999999999999d"), Err("Span '99999999999999999d' is too long".to_string()));
+        let now = Utc::now();
+        assert_eq!(Span::from_str("100000000d").unwrap().start(now), DateTime::<Utc>::MIN_UTC);
+        assert_eq!(Span::from_str("7d").unwrap().start(now), now - Duration::days(7));
+        assert_eq!(Span(Duration::days(14)).to_string(), "2w");
+        assert_eq!(Span(Duration::days(3)).to_string(), "3d");
+    }
+
+    #[test]
+    fn describes_past_dates() {
+        let now = Utc::now();
+        assert_eq!(relative(now - Duration::seconds(20), now), "just now");
+        assert_eq!(relative(now - Duration::minutes(5), now), "5m late");
+        assert_eq!(relative(now - Duration::hours(2), now), "2h late");
+        assert_eq!(relative(now - Duration::days(2), now), "2 days late");
+    }
+}
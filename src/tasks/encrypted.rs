@@ -0,0 +1,387 @@
+use std::{fmt, fs::{self, File}, io::{IsTerminal, Read}, path::{Path, PathBuf}, sync::Mutex};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{aead::{rand_core::RngCore, Aead, OsRng, Payload}, AeadCore, KeyInit, XChaCha20Poly1305, XNonce};
+
+use super::{backup::{self, Backup, Retention}, persistence::{self, Migration, StoreFormat}, store::TaskStore, Tasks, TaskError};
+
+/// Passphrase for scripts, ahead of the keyfile
+pub const PASSPHRASE_ENV: &str = "TASK_MANAGER_PASSPHRASE";
+/// File holding the passphrase, a trailing newline is ignored
+pub const KEYFILE_ENV: &str = "TASK_MANAGER_KEYFILE";
+
+// File layout: MAGIC, the Argon2id memory, time and parallelism costs as
+// little-endian u32s, the salt, the nonce and then the ciphertext. Everything
+// before the ciphertext is authenticated along with it.
+const MAGIC: &[u8; 8] = b"tmcrypt1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 12 + SALT_LEN + NONCE_LEN;
+// The most memory (in KiB), passes and lanes a header may ask for. The header
+// is only authenticated after the key is derived, so a tampered one mustn't
+// be able to exhaust memory or hang every command.
+const MAX_COSTS: [u32; 3] = [1024 * 1024, 64, 64];
+
+// Asked for at most once per run, the load and the save share it
+static PROMPTED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Where an encrypted store gets its passphrase.
+#[derive(Clone)]
+pub enum Passphrase {
+    Given(String),
+    /// `TASK_MANAGER_PASSPHRASE`, then the file named by
+    /// `TASK_MANAGER_KEYFILE`, then a prompt when run in a terminal
+    Ask,
+}
+
+// Keeps passphrases out of debug output
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Passphrase::Given(_) => write!(f, "Given(..)"),
+            Passphrase::Ask => write!(f, "Ask"),
+        }
+    }
+}
+
+impl Passphrase {
+    /// The passphrase, prompting for it twice when `confirm` is set so a typo
+    /// can't lock the tasks away.
+    pub fn resolve(&self, confirm: bool) -> Result<String, TaskError> {
+        if let Passphrase::Given(passphrase) = self {
+            return Ok(passphrase.clone());
+        }
+        if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) {
+            return Ok(passphrase);
+        }
+        if let Some(keyfile) = std::env::var_os(KEYFILE_ENV).filter(|p| !p.is_empty()) {
+            let contents = fs::read_to_string(&keyfile)
+                .map_err(|e| TaskError::Encryption(format!("can't read keyfile {}: {}", Path::new(&keyfile).display(), e)))?;
+            let passphrase = contents.strip_suffix('\n').map(|p| p.strip_suffix('\r').unwrap_or(p)).unwrap_or(&contents);
+            return check_not_empty(passphrase.to_string());
+        }
+        prompt(confirm)
+    }
+}
+
+fn prompt(confirm: bool) -> Result<String, TaskError> {
+    let mut prompted = PROMPTED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(passphrase) = prompted.as_ref() {
+        return Ok(passphrase.clone());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(TaskError::Encryption(format!("no passphrase, set {} or {} when not running in a terminal", PASSPHRASE_ENV, KEYFILE_ENV)));
+    }
+
+    let passphrase = check_not_empty(rpassword::prompt_password("Passphrase: ")?)?;
+    if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        return Err(TaskError::Encryption("the passphrases don't match".to_string()));
+    }
+    *prompted = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+fn check_not_empty(passphrase: String) -> Result<String, TaskError> {
+    if passphrase.is_empty() {
+        Err(TaskError::Encryption("the passphrase is empty".to_string()))
+    } else {
+        Ok(passphrase)
+    }
+}
+
+/// Whether the file at `path` was written by an `EncryptedStore`. False when
+/// it doesn't exist.
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut magic = [0; MAGIC.len()];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == MAGIC
+}
+
+/// Encrypts `plain` with a key derived from `passphrase` and a fresh salt.
+pub fn encrypt(plain: &[u8], passphrase: &str) -> Result<Vec<u8>, TaskError> {
+    Key::derive(passphrase, &Params::default())?.seal(plain)
+}
+
+// A derived key, so several files can be sealed for the cost of one
+// derivation. Each file still gets its own nonce.
+struct Key {
+    params: Params,
+    salt: [u8; SALT_LEN],
+    cipher: XChaCha20Poly1305,
+}
+
+impl Key {
+    fn derive(passphrase: &str, params: &Params) -> Result<Key, TaskError> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Ok(Key { params: params.clone(), salt, cipher: cipher(passphrase, params, &salt)? })
+    }
+
+    fn seal(&self, plain: &[u8]) -> Result<Vec<u8>, TaskError> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut data = Vec::with_capacity(HEADER_LEN + plain.len() + 16);
+        data.extend_from_slice(MAGIC);
+        for cost in [self.params.m_cost(), self.params.t_cost(), self.params.p_cost()] {
+            data.extend_from_slice(&cost.to_le_bytes());
+        }
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+
+        let sealed = self.cipher
+            .encrypt(&nonce, Payload { msg: plain, aad: &data })
+            .map_err(|_| TaskError::Encryption("encryption failed".to_string()))?;
+        data.extend_from_slice(&sealed);
+        Ok(data)
+    }
+}
+
+/// Decrypts what `encrypt` wrote. A wrong passphrase and a modified file both
+/// give `TaskError::WrongPassphrase`.
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, TaskError> {
+    if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err(TaskError::Encryption("not an encrypted tasks file".to_string()));
+    }
+    let (header, sealed) = data.split_at(HEADER_LEN);
+    let cost = |index: usize| {
+        let start = MAGIC.len() + index * 4;
+        u32::from_le_bytes([header[start], header[start + 1], header[start + 2], header[start + 3]])
+    };
+    if (0..3).any(|index| cost(index) > MAX_COSTS[index]) {
+        return Err(TaskError::Encryption("the key parameters are out of range, the file has been modified".to_string()));
+    }
+    let params = Params::new(cost(0), cost(1), cost(2), None)
+        .map_err(|e| TaskError::Encryption(format!("unreadable key parameters: {}", e)))?;
+    let salt = &header[MAGIC.len() + 12..MAGIC.len() + 12 + SALT_LEN];
+    let nonce = XNonce::from_slice(&header[HEADER_LEN - NONCE_LEN..]);
+
+    cipher(passphrase, &params, salt)?
+        .decrypt(nonce, Payload { msg: sealed, aad: header })
+        .map_err(|_| TaskError::WrongPassphrase)
+}
+
+fn cipher(passphrase: &str, params: &Params, salt: &[u8]) -> Result<XChaCha20Poly1305, TaskError> {
+    let mut key = [0; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| TaskError::Encryption(format!("key derivation failed: {}", e)))?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
+
+/// A YAML, JSON or TOML tasks file encrypted with a passphrase. Backups are
+/// copies of the encrypted file.
+#[derive(Debug)]
+pub struct EncryptedStore {
+    pub path: PathBuf,
+    /// The text format inside the encryption
+    pub format: StoreFormat,
+    pub retention: Retention,
+    pub passphrase: Passphrase,
+}
+
+impl EncryptedStore {
+    fn read(&self, path: &Path) -> Result<Tasks, TaskError> {
+        let data = fs::read(path)?;
+        let plain = decrypt(&data, &self.passphrase.resolve(false)?)?;
+        let text = String::from_utf8(plain).map_err(|e| TaskError::Encryption(format!("decrypted data isn't text: {}", e)))?;
+        persistence::from_str(&text, self.format)
+    }
+
+    fn write(&self, tasks: &Tasks) -> Result<(), TaskError> {
+        let plain = persistence::to_string(tasks, self.format)?;
+        let data = encrypt(plain.as_bytes(), &self.passphrase.resolve(false)?)?;
+        backup::create(&self.path, &self.retention, chrono::Utc::now())?;
+        persistence::write_atomic(&self.path, &data)
+    }
+
+    /// Replaces a plain file with `tasks` encrypted, as `tasks encrypt` does.
+    /// The plain file isn't backed up, and the plain backups and pre-migration
+    /// copies next to it are encrypted in place. Returns how many of those
+    /// there were. A passphrase not asked for yet is asked for twice.
+    pub fn encrypt_plain_file(&self, tasks: &Tasks) -> Result<usize, TaskError> {
+        self.encrypt_plain_file_with(tasks, &Params::default())
+    }
+
+    fn encrypt_plain_file_with(&self, tasks: &Tasks, params: &Params) -> Result<usize, TaskError> {
+        let key = Key::derive(&self.passphrase.resolve(true)?, params)?;
+        let copies = plain_copies(&self.path)?;
+        for copy in &copies {
+            persistence::write_atomic(copy, &key.seal(&fs::read(copy)?)?)?;
+        }
+        let plain = persistence::to_string(tasks, self.format)?;
+        persistence::write_atomic(&self.path, &key.seal(plain.as_bytes())?)?;
+        Ok(copies.len())
+    }
+}
+
+// Copies of the file at `path` that aren't encrypted yet: its backups and the
+// `<file>.v<N>.bak` files kept by migrations
+fn plain_copies(path: &Path) -> Result<Vec<PathBuf>, TaskError> {
+    let mut copies: Vec<PathBuf> = backup::list(path)?.into_iter().map(|b| b.path).collect();
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_migration_copy = file_name
+            .strip_prefix(&name)
+            .and_then(|rest| rest.strip_prefix(".v"))
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .is_some_and(|version| version.parse::<u32>().is_ok());
+        if is_migration_copy {
+            copies.push(entry.path());
+        }
+    }
+    copies.retain(|copy| !is_encrypted(copy));
+    Ok(copies)
+}
+
+impl TaskStore for EncryptedStore {
+    fn load(&self) -> Result<Tasks, TaskError> {
+        match self.read(&self.path) {
+            Err(TaskError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => Err(TaskError::NoFile),
+            result => result,
+        }
+    }
+
+    // Checked first so a wrong passphrase can't replace the file. Unchanged
+    // tasks aren't written, since every run saves
+    fn save(&self, tasks: &Tasks) -> Result<(), TaskError> {
+        if self.path.exists() {
+            let current = self.read(&self.path)?;
+            if persistence::to_string(&current, self.format)? == persistence::to_string(tasks, self.format)? {
+                return Ok(());
+            }
+        }
+        self.write(tasks)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Files are always encrypted in the current schema
+    fn plan_migration(&self) -> Result<Migration, TaskError> {
+        self.read(&self.path)?;
+        Ok(Migration { from: persistence::SCHEMA_VERSION, to: persistence::SCHEMA_VERSION, changes: Vec::new() })
+    }
+
+    fn restore(&self, name: &str) -> Option<Result<Backup, TaskError>> {
+        let restore = || {
+            let restored = backup::find(&self.path, name)?;
+            self.read(&restored.path)
+                .map_err(|e| TaskError::InvalidInput(format!("{} can't be loaded: {}", restored.name, e)))?;
+            let data = fs::read(&restored.path)?;
+            backup::create(&self.path, &self.retention, chrono::Utc::now())?;
+            persistence::write_atomic(&self.path, &data)?;
+            Ok(restored)
+        };
+        Some(restore())
+    }
+
+    fn decrypt(&self, tasks: &Tasks) -> Option<Result<(), TaskError>> {
+        let decrypt = || {
+            let plain = persistence::to_string(tasks, self.format)?;
+            backup::create(&self.path, &self.retention, chrono::Utc::now())?;
+            persistence::write_atomic(&self.path, plain.as_bytes())
+        };
+        Some(decrypt())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    // The default costs take seconds in debug builds
+    fn cheap() -> Params {
+        Params::new(64, 1, 1, None).unwrap()
+    }
+
+    #[test]
+    fn round_trips_and_rejects_wrong_passphrases() {
+        let data = Key::derive("correct horse", &cheap()).unwrap().seal(b"tasks: []").unwrap();
+
+        assert!(!data.windows(5).any(|w| w == b"tasks"));
+        assert_eq!(decrypt(&data, "correct horse").unwrap(), b"tasks: []");
+        assert!(matches!(decrypt(&data, "wrong horse"), Err(TaskError::WrongPassphrase)));
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(decrypt(&tampered, "correct horse"), Err(TaskError::WrongPassphrase)));
+        assert!(matches!(decrypt(b"tasks: []", "correct horse"), Err(TaskError::Encryption(_))));
+    }
+
+    #[test]
+    fn rejects_costs_beyond_the_limits() {
+        let data = Key::derive("correct horse", &cheap()).unwrap().seal(b"tasks: []").unwrap();
+
+        // Memory, passes and lanes in turn
+        for index in 0..3 {
+            let mut tampered = data.clone();
+            let start = MAGIC.len() + index * 4;
+            tampered[start..start + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert!(matches!(decrypt(&tampered, "correct horse"), Err(TaskError::Encryption(_))));
+        }
+    }
+
+    #[test]
+    fn store_saves_encrypted_and_loads_back() {
+        let dir = env::temp_dir().join("encrypted_store_saves_encrypted");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.yaml");
+        let store = |passphrase: &str| EncryptedStore {
+            path: path.clone(),
+            format: StoreFormat::Yaml,
+            retention: Retention::none(),
+            passphrase: Passphrase::Given(passphrase.to_string()),
+        };
+
+        let mut tasks = Tasks::new();
+        tasks.add_task("Call Acme Corp".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        store("secret").save(&tasks).unwrap();
+
+        assert!(is_encrypted(&path));
+        assert!(!String::from_utf8_lossy(&fs::read(&path).unwrap()).contains("Acme"));
+        assert_eq!(store("secret").load().unwrap().tasks, tasks.tasks);
+        assert!(matches!(store("guess").load(), Err(TaskError::WrongPassphrase)));
+        assert!(matches!(store("guess").save(&Tasks::new()), Err(TaskError::WrongPassphrase)));
+
+        store("secret").decrypt(&tasks).unwrap().unwrap();
+        assert!(!is_encrypted(&path));
+        assert_eq!(persistence::load_in(&path, StoreFormat::Yaml).unwrap().tasks, tasks.tasks);
+    }
+
+    #[test]
+    fn encrypting_a_plain_file_leaves_no_plain_copies() {
+        let dir = env::temp_dir().join("encrypted_leaves_no_plain_copies");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.yaml");
+        let mut tasks = Tasks::new();
+        tasks.add_task("Call Acme Corp".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        persistence::save_in(&Tasks::new(), &path, StoreFormat::Yaml, &Retention::default()).unwrap();
+        persistence::save_in(&tasks, &path, StoreFormat::Yaml, &Retention::default()).unwrap();
+        fs::write(persistence::backup_path(&path, 1), "tasks: []\n").unwrap();
+
+        let store = EncryptedStore {
+            path: path.clone(),
+            format: StoreFormat::Yaml,
+            retention: Retention::default(),
+            passphrase: Passphrase::Given("secret".to_string()),
+        };
+        assert_eq!(store.encrypt_plain_file_with(&tasks, &cheap()).unwrap(), 2);
+
+        let mut files = vec![path.clone(), persistence::backup_path(&path, 1)];
+        files.extend(backup::list(&path).unwrap().into_iter().map(|b| b.path));
+        assert_eq!(files.len(), 3);
+        for file in files {
+            assert!(is_encrypted(&file), "{} is still plain", file.display());
+        }
+        assert_eq!(store.load().unwrap().tasks, tasks.tasks);
+        let backup = &backup::list(&path).unwrap()[0];
+        assert!(store.read(&backup.path).unwrap().tasks.is_empty());
+    }
+}
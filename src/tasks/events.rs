@@ -0,0 +1,423 @@
+use std::{cell::RefCell, fs, io::{Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{persistence::{self, Migration, SCHEMA_VERSION}, store::TaskStore, Task, Tasks, TaskError};
+
+/// A single mutation, as recorded in the event log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Change {
+    Add { task: Task },
+    /// Any edit other than completing, with the task as it is afterwards
+    Update { task: Task },
+    Complete { id: u32, completed_at: Option<DateTime<Utc>> },
+    Delete { id: u32 },
+}
+
+impl Change {
+    fn apply(&self, tasks: &mut Tasks) {
+        match self {
+            Change::Add { task } => {
+                tasks.next_id = tasks.next_id.max(task.id + 1);
+                tasks.tasks.push(task.clone());
+            }
+            Change::Update { task } => {
+                if let Some(existing) = tasks.tasks.iter_mut().find(|t| t.id == task.id) {
+                    *existing = task.clone();
+                }
+            }
+            Change::Complete { id, completed_at } => {
+                if let Some(existing) = tasks.tasks.iter_mut().find(|t| t.id == *id) {
+                    existing.completed = true;
+                    existing.completed_at = *completed_at;
+                }
+            }
+            Change::Delete { id } => tasks.tasks.retain(|t| t.id != *id),
+        }
+    }
+}
+
+/// A numbered, timestamped line of the event log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+// The state after every event up to `seq`, written by `compact`
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    seq: u64,
+    /// When the last folded event happened, history before it is gone
+    at: Option<DateTime<Utc>>,
+    next_id: u32,
+    tasks: Vec<Task>,
+}
+
+// The state after the last event loaded or recorded
+#[derive(Debug, Clone, PartialEq)]
+struct Replayed {
+    tasks: Vec<Task>,
+    next_id: u32,
+    seq: u64,
+}
+
+/// An append-only log with one JSON line per change, plus an optional
+/// snapshot next to it. Loading replays the log on top of the snapshot, so
+/// saving only appends what changed and the log doubles as an audit trail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventStore {
+    pub path: PathBuf,
+    // Kept from `load` so `record` diffs against it instead of replaying again
+    replayed: RefCell<Option<Replayed>>,
+}
+
+impl EventStore {
+    pub fn new(path: impl Into<PathBuf>) -> EventStore {
+        EventStore { path: path.into(), replayed: RefCell::new(None) }
+    }
+
+    /// `tasks.events.snapshot` next to the log.
+    pub fn snapshot_path(&self) -> PathBuf {
+        persistence::sibling(&self.path, ".snapshot")
+    }
+
+    /// Every event still in the log, oldest first.
+    pub fn events(&self) -> Result<Vec<Event>, TaskError> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let lines: Vec<&str> = data.lines().collect();
+        let mut events = Vec::new();
+        for (number, line) in lines.iter().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                // A write cut short by a crash, the change never completed
+                Err(_) if number + 1 == lines.len() && !data.ends_with('\n') => break,
+                Err(err) => {
+                    return Err(TaskError::InvalidInput(format!("{} line {}: {}", self.path.display(), number + 1, err)));
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    fn read_snapshot(&self) -> Result<Option<Snapshot>, TaskError> {
+        let data = match fs::read_to_string(self.snapshot_path()) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let snapshot: Snapshot = serde_json::from_str(&data)?;
+        if snapshot.version > SCHEMA_VERSION {
+            return Err(TaskError::InvalidInput(format!(
+                "the snapshot has schema version {}, but this build only understands up to {}; upgrade task-manager",
+                snapshot.version, SCHEMA_VERSION
+            )));
+        }
+        Ok(Some(snapshot))
+    }
+
+    // The tasks after replaying events up to `until`, and the last sequence
+    // number seen
+    fn replay(&self, until: Option<DateTime<Utc>>) -> Result<(Tasks, u64), TaskError> {
+        let snapshot = self.read_snapshot()?;
+        if snapshot.is_none() && !self.path.exists() {
+            return Err(TaskError::NoFile);
+        }
+
+        let (mut tasks, mut seq) = match snapshot {
+            Some(snapshot) => {
+                if let (Some(until), Some(at)) = (until, snapshot.at) {
+                    if until < at {
+                        return Err(TaskError::InvalidInput(format!("history before {} was compacted", at.format("%Y-%m-%d %H:%M"))));
+                    }
+                }
+                (Tasks { tasks: snapshot.tasks, next_id: snapshot.next_id }, snapshot.seq)
+            }
+            None => (Tasks::new(), 0),
+        };
+
+        // Events already in the snapshot are left over from an interrupted compaction
+        let folded = seq;
+        for event in self.events()?.into_iter().filter(|e| e.seq > folded) {
+            if until.is_some_and(|until| event.at > until) {
+                break;
+            }
+            event.change.apply(&mut tasks);
+            seq = event.seq;
+        }
+
+        Ok((tasks, seq))
+    }
+
+    /// Appends the changes between the stored tasks and `tasks`, stamped `at`.
+    /// The stored tasks are the ones last loaded or recorded, so only the
+    /// first save without a `load` replays the log. The caller's lock keeps
+    /// other processes from appending in between.
+    pub fn record(&self, tasks: &Tasks, at: DateTime<Utc>) -> Result<usize, TaskError> {
+        // Taken, so a failed append replays the log next time
+        let (stored, seq) = match self.replayed.take() {
+            Some(replayed) => (Tasks { tasks: replayed.tasks, next_id: replayed.next_id }, replayed.seq),
+            None => match self.replay(None) {
+                Err(TaskError::NoFile) => (Tasks::new(), 0),
+                result => result?,
+            },
+        };
+
+        let mut lines = String::new();
+        let changes = diff(&stored, tasks);
+        for (offset, change) in changes.iter().enumerate() {
+            let event = Event { seq: seq + 1 + offset as u64, at, change: change.clone() };
+            lines.push_str(&serde_json::to_string(&event)?);
+            lines.push('\n');
+        }
+
+        if !lines.is_empty() {
+            let mut file = fs::OpenOptions::new().create(true).read(true).append(true).open(&self.path)?;
+            // Cut off a torn last line, so it doesn't run into the new events,
+            // reading the whole log only when there is one
+            let mut last = [b'\n'];
+            if file.metadata()?.len() > 0 {
+                file.seek(SeekFrom::End(-1))?;
+                file.read_exact(&mut last)?;
+            }
+            if last[0] != b'\n' {
+                let data = fs::read(&self.path)?;
+                file.set_len(data.iter().rposition(|b| *b == b'\n').map_or(0, |i| i as u64 + 1))?;
+            }
+            file.write_all(lines.as_bytes())?;
+            file.sync_data()?;
+        }
+
+        self.replayed.replace(Some(Replayed { tasks: tasks.tasks.clone(), next_id: tasks.next_id, seq: seq + changes.len() as u64 }));
+        Ok(changes.len())
+    }
+
+    /// Folds events before `before` (or all of them) into the snapshot and
+    /// drops them from the log. Returns how many were folded.
+    pub fn compact_before(&self, before: Option<DateTime<Utc>>) -> Result<usize, TaskError> {
+        let snapshot = self.read_snapshot()?;
+        let (mut tasks, mut seq, mut at) = match snapshot {
+            Some(snapshot) => (Tasks { tasks: snapshot.tasks, next_id: snapshot.next_id }, snapshot.seq, snapshot.at),
+            None => (Tasks::new(), 0, None),
+        };
+
+        let events: Vec<Event> = self.events()?.into_iter().filter(|e| e.seq > seq).collect();
+        let folded = events.iter().take_while(|e| before.is_none_or(|before| e.at < before)).count();
+        if folded == 0 {
+            return Ok(0);
+        }
+        for event in &events[..folded] {
+            event.change.apply(&mut tasks);
+            seq = event.seq;
+            at = Some(event.at);
+        }
+
+        // The snapshot goes first, so a crash in between only leaves events
+        // that replay skips
+        let snapshot = Snapshot { version: SCHEMA_VERSION, seq, at, next_id: tasks.next_id, tasks: tasks.tasks };
+        persistence::write_atomic(&self.snapshot_path(), (serde_json::to_string_pretty(&snapshot)? + "\n").as_bytes())?;
+
+        let mut rest = String::new();
+        for event in &events[folded..] {
+            rest.push_str(&serde_json::to_string(event)?);
+            rest.push('\n');
+        }
+        persistence::write_atomic(&self.path, rest.as_bytes())?;
+
+        Ok(folded)
+    }
+}
+
+impl TaskStore for EventStore {
+    fn load(&self) -> Result<Tasks, TaskError> {
+        let (tasks, seq) = self.replay(None)?;
+        self.replayed.replace(Some(Replayed { tasks: tasks.tasks.clone(), next_id: tasks.next_id, seq }));
+        Ok(tasks)
+    }
+
+    fn save(&self, tasks: &Tasks) -> Result<(), TaskError> {
+        self.record(tasks, Utc::now()).map(|_| ())
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn plan_migration(&self) -> Result<Migration, TaskError> {
+        let from = match self.read_snapshot()? {
+            Some(snapshot) => snapshot.version,
+            None if self.path.exists() => SCHEMA_VERSION,
+            None => return Err(TaskError::NoFile),
+        };
+        Ok(Migration { from, to: SCHEMA_VERSION, changes: Vec::new() })
+    }
+
+    fn as_of(&self, at: DateTime<Utc>) -> Option<Result<Tasks, TaskError>> {
+        Some(self.replay(Some(at)).map(|(tasks, _)| tasks))
+    }
+
+    fn compact(&self, before: Option<DateTime<Utc>>) -> Option<Result<usize, TaskError>> {
+        Some(self.compact_before(before))
+    }
+}
+
+// The changes that turn `old` into `new`: deletes, then adds and edits in the
+// order of `new`
+fn diff(old: &Tasks, new: &Tasks) -> Vec<Change> {
+    let mut changes: Vec<Change> = old
+        .tasks
+        .iter()
+        .filter(|t| !new.tasks.iter().any(|n| n.id == t.id))
+        .map(|t| Change::Delete { id: t.id })
+        .collect();
+
+    for task in &new.tasks {
+        match old.tasks.iter().find(|t| t.id == task.id) {
+            None => changes.push(Change::Add { task: task.clone() }),
+            Some(previous) if previous == task => {}
+            Some(previous) => {
+                let completed = Task { completed: true, completed_at: task.completed_at, ..previous.clone() };
+                if !previous.completed && completed == *task {
+                    changes.push(Change::Complete { id: task.id, completed_at: task.completed_at });
+                } else {
+                    changes.push(Change::Update { task: task.clone() });
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn store(name: &str) -> EventStore {
+        let store = EventStore::new(env::temp_dir().join(format!("{}.events", name)));
+        let _ = fs::remove_file(&store.path);
+        let _ = fs::remove_file(store.snapshot_path());
+        store
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    // Adds two tasks on the 1st, completes one on the 2nd, deletes the other
+    // and adds a third on the 3rd
+    fn history(store: &EventStore) -> Tasks {
+        let mut tasks = Tasks::new();
+        tasks.add_task("Write report".to_string(), "".to_string(), "2024-05-10T09:00:00Z".to_string());
+        tasks.add_task("Call Bob".to_string(), "".to_string(), "2024-05-11T09:00:00Z".to_string());
+        store.record(&tasks, at("2024-05-01T09:00:00Z")).unwrap();
+
+        tasks.complete_task(1).unwrap();
+        store.record(&tasks, at("2024-05-02T09:00:00Z")).unwrap();
+
+        tasks.delete_task(2);
+        tasks.add_task("Book venue".to_string(), "".to_string(), "2024-05-12T09:00:00Z".to_string());
+        store.record(&tasks, at("2024-05-03T09:00:00Z")).unwrap();
+        tasks
+    }
+
+    #[test]
+    fn records_each_change_as_an_event() {
+        let store = store("events_records_each_change");
+        assert!(matches!(store.load(), Err(TaskError::NoFile)));
+        let tasks = history(&store);
+
+        let kinds: Vec<String> = store
+            .events()
+            .unwrap()
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap()["event"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, vec!["add", "add", "complete", "delete", "add"]);
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.tasks, tasks.tasks);
+        assert_eq!(loaded.next_id, 4);
+
+        // Nothing changed, nothing appended
+        assert_eq!(store.record(&tasks, Utc::now()).unwrap(), 0);
+    }
+
+    #[test]
+    fn records_against_the_loaded_tasks() {
+        let store = store("events_records_against_the_loaded_tasks");
+        history(&store);
+        let reopened = EventStore::new(&store.path);
+        let mut tasks = reopened.load().unwrap();
+
+        // The log isn't read again, so emptying it behind the store's back
+        // leaves only the new event
+        fs::write(&store.path, "").unwrap();
+        tasks.tasks[1].completed = true;
+        assert_eq!(reopened.record(&tasks, at("2024-05-04T09:00:00Z")).unwrap(), 1);
+
+        let events = store.events().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seq, 6);
+        assert!(matches!(events[0].change, Change::Complete { id: 3, .. }));
+    }
+
+    #[test]
+    fn replays_to_a_point_in_time() {
+        let store = store("events_replays_to_a_point_in_time");
+        history(&store);
+
+        let names = |tasks: Tasks| tasks.tasks.iter().map(|t| (t.name.clone(), t.completed)).collect::<Vec<_>>();
+        assert_eq!(
+            names(store.as_of(at("2024-05-02T12:00:00Z")).unwrap().unwrap()),
+            vec![("Write report".to_string(), true), ("Call Bob".to_string(), false)]
+        );
+        assert!(store.as_of(at("2024-04-30T00:00:00Z")).unwrap().unwrap().tasks.is_empty());
+    }
+
+    #[test]
+    fn compacts_into_a_snapshot() {
+        let store = store("events_compacts_into_a_snapshot");
+        let tasks = history(&store);
+
+        assert_eq!(store.compact_before(Some(at("2024-05-03T00:00:00Z"))).unwrap(), 3);
+        assert_eq!(store.events().unwrap().len(), 2);
+        assert_eq!(store.load().unwrap().tasks, tasks.tasks);
+        assert_eq!(store.as_of(at("2024-05-02T12:00:00Z")).unwrap().unwrap().tasks.len(), 2);
+        assert!(store.as_of(at("2024-05-01T12:00:00Z")).unwrap().is_err());
+
+        assert_eq!(store.compact_before(None).unwrap(), 2);
+        assert!(store.events().unwrap().is_empty());
+        assert_eq!(store.load().unwrap().tasks, tasks.tasks);
+        assert_eq!(store.load().unwrap().next_id, 4);
+    }
+
+    #[test]
+    fn ignores_a_torn_last_line() {
+        let store = store("events_ignores_a_torn_last_line");
+        let tasks = history(&store);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&store.path).unwrap();
+        file.write_all(br#"{"seq":6,"at":"2024-05-04T09:00:00Z","event":"del"#).unwrap();
+
+        assert_eq!(store.load().unwrap().tasks, tasks.tasks);
+
+        let mut tasks = tasks;
+        tasks.delete_task(3);
+        store.record(&tasks, at("2024-05-05T09:00:00Z")).unwrap();
+        assert_eq!(store.events().unwrap().last().unwrap().change, Change::Delete { id: 3 });
+    }
+}
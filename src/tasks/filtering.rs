@@ -125,6 +125,34 @@ impl fmt::Display for CompletionFilter {
     }
 }
 
+/// Keeps tasks in `project` (when given) that carry `tag` (when given).
+pub fn filter_project_tag(tasks: &[Task], project: Option<&str>, tag: Option<&str>) -> Vec<Task> {
+    tasks.iter()
+        .filter(|t| project.is_none_or(|p| t.project.as_deref() == Some(p)))
+        .filter(|t| tag.is_none_or(|tag| t.tags.iter().any(|t| t == tag)))
+        .cloned()
+        .collect()
+}
+
+/// The filters a command applies, bundled so a store that can filter itself
+/// (see `TaskStore::query`) gets them all at once.
+#[derive(Debug, Default)]
+pub struct TaskQuery<'a> {
+    pub due: Option<&'a DueFilter>,
+    pub status: Option<&'a CompletionFilter>,
+    pub project: Option<&'a str>,
+    pub tag: Option<&'a str>,
+}
+
+impl TaskQuery<'_> {
+    /// Applies the query to tasks already in memory.
+    pub fn filter(&self, tasks: &[Task]) -> Vec<Task> {
+        let filtered = self.due.unwrap_or(&DueFilter::All).filter(tasks);
+        let filtered = self.status.unwrap_or(&CompletionFilter::All).filter(&filtered);
+        filter_project_tag(&filtered, self.project, self.tag)
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -246,4 +274,16 @@ mod tests {
         assert_eq!(format!("{}", CompletionFilter::Incomplete), "incomplete");
     }
     
-} 
+
+    #[test]
+    fn filters_by_project_and_tag() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[0].project = Some("work".to_string());
+        tasks.tasks[1].project = Some("work".to_string());
+        tasks.tasks[1].tags = vec!["urgent".to_string()];
+
+        assert_eq!(filter_project_tag(&tasks.tasks, Some("work"), None).len(), 2);
+        assert_eq!(filter_project_tag(&tasks.tasks, Some("work"), Some("urgent"))[0].id, 2);
+        assert_eq!(filter_project_tag(&tasks.tasks, None, None).len(), 3);
+    }
+}
@@ -0,0 +1,227 @@
+use std::io::Write;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::{dates, Task, TaskError};
+
+const TRIALS: usize = 10_000;
+// Give up on trials that would run longer than this
+const MAX_WEEKS: f64 = 520.0;
+
+/// Finish date estimates for a set of open tasks, from a Monte Carlo
+/// simulation drawing random weeks out of the recent completion history.
+#[derive(Debug)]
+pub struct Forecast<'a> {
+    pub open: usize,
+    /// Tasks completed in each sampled week, most recent first
+    pub samples: Vec<usize>,
+    /// 15th, 50th and 85th percentile of the simulated finish dates
+    pub optimistic: DateTime<Utc>,
+    pub likely: DateTime<Utc>,
+    pub pessimistic: DateTime<Utc>,
+    /// Open tasks due before the likely finish, soonest first
+    pub at_risk: Vec<&'a Task>,
+}
+
+impl<'a> Forecast<'a> {
+    /// Forecasts the open tasks in `tasks` from the throughput of `history`
+    /// over the last `weeks` weeks. Returns `None` when nothing was completed
+    /// in that time, since there is then no throughput to project from.
+    pub fn new(tasks: &'a [Task], history: &[Task], now: DateTime<Utc>, weeks: u32, seed: u64) -> Option<Forecast<'a>> {
+        let samples = weekly_throughput(history, now, weeks);
+        if samples.iter().all(|s| *s == 0) {
+            return None;
+        }
+
+        let mut open: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+        open.sort_by_key(|t| (t.due_date, t.id));
+
+        let mut rng = SplitMix64(seed);
+        let mut trials: Vec<f64> = (0..TRIALS).map(|_| simulate(open.len(), &samples, &mut rng)).collect();
+        trials.sort_by(|a, b| a.total_cmp(b));
+
+        let finish = |percentile: usize| {
+            let weeks = trials[(trials.len() - 1) * percentile / 100];
+            now + Duration::seconds((weeks * 7.0 * 24.0 * 3600.0) as i64)
+        };
+        let likely = finish(50);
+
+        Some(Forecast {
+            open: open.len(),
+            samples,
+            optimistic: finish(15),
+            likely,
+            pessimistic: finish(85),
+            at_risk: open.into_iter().filter(|t| t.due_date < likely).collect(),
+        })
+    }
+
+    pub fn average(&self) -> f64 {
+        self.samples.iter().sum::<usize>() as f64 / self.samples.len() as f64
+    }
+
+    pub fn write(&self, writer: &mut impl Write, now: DateTime<Utc>) -> Result<(), TaskError> {
+        if self.open == 0 {
+            writeln!(writer, "No open tasks to forecast")?;
+            return Ok(());
+        }
+
+        writeln!(
+            writer,
+            "{} open tasks, {:.1} completed per week over the last {} weeks",
+            self.open,
+            self.average(),
+            self.samples.len()
+        )?;
+
+        for (label, date) in [("Optimistic", self.optimistic), ("Likely", self.likely), ("Pessimistic", self.pessimistic)] {
+            writeln!(writer, "  {:<12} {}  ({})", label, date.format("%Y-%m-%d"), dates::relative(date, now))?;
+        }
+
+        if !self.at_risk.is_empty() {
+            writeln!(writer)?;
+            writeln!(writer, "Due before the likely finish:")?;
+            for task in &self.at_risk {
+                writeln!(writer, "  #{} {} (due {})", task.id, task.name, task.due_date.format("%Y-%m-%d"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Completions in each of the last `weeks` seven day windows ending at `now`,
+/// most recent first. Only looks back as far as the first recorded
+/// completion, so a short history isn't padded with empty weeks.
+pub fn weekly_throughput(tasks: &[Task], now: DateTime<Utc>, weeks: u32) -> Vec<usize> {
+    let completions: Vec<DateTime<Utc>> = tasks
+        .iter()
+        .filter(|t| t.completed)
+        .filter_map(|t| t.completed_at)
+        .filter(|at| *at <= now)
+        .collect();
+
+    let Some(first) = completions.iter().min() else {
+        return Vec::new();
+    };
+    let recorded = ((now - *first).num_days() / 7 + 1) as u32;
+
+    (0..weeks.min(recorded).max(1))
+        .map(|week| {
+            let end = now - Duration::weeks(week as i64);
+            let start = end - Duration::weeks(1);
+            completions.iter().filter(|at| **at > start && **at <= end).count()
+        })
+        .collect()
+}
+
+// Weeks until `remaining` tasks are done, drawing each week's throughput from
+// `samples`. The last week counts partially.
+fn simulate(mut remaining: usize, samples: &[usize], rng: &mut SplitMix64) -> f64 {
+    let mut weeks = 0.0;
+    while remaining > 0 && weeks < MAX_WEEKS {
+        let done = samples[rng.below(samples.len())];
+        if done >= remaining {
+            return weeks + remaining as f64 / done as f64;
+        }
+        remaining -= done;
+        weeks += 1.0;
+    }
+    weeks
+}
+
+// Small seeded generator, plenty for sampling and keeps runs reproducible
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::{done, task};
+
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        "2024-05-10T12:00:00Z".parse().unwrap()
+    }
+
+    // Two completions in each of the last four weeks, then six open tasks
+    fn sample() -> Vec<Task> {
+        let mut tasks: Vec<Task> = (0..8)
+            .map(|i| done(i + 1, &(now() - Duration::days(1 + 7 * (i as i64 / 2))).to_rfc3339()))
+            .collect();
+        tasks.push(task(9, "Task 9", "2024-05-12T09:00:00Z"));
+        tasks.push(task(10, "Task 10", "2024-06-05T09:00:00Z"));
+        tasks.extend((11..15).map(|id| task(id, &format!("Task {}", id), "2024-08-01T09:00:00Z")));
+        tasks
+    }
+
+    #[test]
+    fn samples_weeks_since_the_first_completion() {
+        assert_eq!(weekly_throughput(&sample(), now(), 12), vec![2, 2, 2, 2]);
+        assert_eq!(weekly_throughput(&sample(), now(), 2), vec![2, 2]);
+        assert!(weekly_throughput(&[], now(), 12).is_empty());
+    }
+
+    #[test]
+    fn projects_steady_throughput() {
+        let tasks = sample();
+        let forecast = Forecast::new(&tasks, &tasks, now(), 12, 1).unwrap();
+
+        // Six tasks at two a week is exactly three weeks, whatever is drawn
+        assert_eq!(forecast.open, 6);
+        assert_eq!(forecast.likely, now() + Duration::weeks(3));
+        assert_eq!(forecast.optimistic, forecast.likely);
+        assert_eq!(forecast.pessimistic, forecast.likely);
+        let at_risk: Vec<u32> = forecast.at_risk.iter().map(|t| t.id).collect();
+        assert_eq!(at_risk, vec![9]);
+    }
+
+    #[test]
+    fn spreads_uneven_throughput() {
+        let mut tasks = sample();
+        // Move the completions of one week into another
+        tasks[2].completed_at = tasks[0].completed_at;
+        tasks[3].completed_at = tasks[0].completed_at;
+
+        let forecast = Forecast::new(&tasks, &tasks, now(), 12, 7).unwrap();
+
+        assert_eq!(forecast.samples, vec![4, 0, 2, 2]);
+        assert!(forecast.optimistic < forecast.pessimistic);
+        assert!(forecast.optimistic <= forecast.likely && forecast.likely <= forecast.pessimistic);
+    }
+
+    #[test]
+    fn needs_some_completions() {
+        let tasks = vec![task(1, "Task 1", "2024-05-12T09:00:00Z")];
+
+        assert!(Forecast::new(&tasks, &tasks, now(), 12, 1).is_none());
+    }
+
+    #[test]
+    fn writes_range_and_tasks_at_risk() {
+        let tasks = sample();
+        let forecast = Forecast::new(&tasks, &tasks, now(), 12, 1).unwrap();
+
+        let mut writer = Vec::new();
+        forecast.write(&mut writer, now()).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+
+        assert!(output.starts_with("6 open tasks, 2.0 completed per week over the last 4 weeks\n"));
+        assert!(output.contains("  Likely       2024-05-31  (in 21 days)\n"));
+        assert!(output.contains("Due before the likely finish:\n  #9 Task 9 (due 2024-05-12)\n"));
+    }
+}
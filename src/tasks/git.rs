@@ -0,0 +1,472 @@
+use std::{collections::HashSet, path::{Component, Path, PathBuf}, process::{Command, Output}};
+
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+
+use super::{persistence::{self, StoreFormat}, Task, Tasks, TaskError};
+
+/// The `git` section of the config. Its presence turns on a commit of the
+/// tasks file after every change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GitSync {
+    /// Remote for `tasks sync`, a URL or a path such as a local bare repo
+    pub remote: Option<String>,
+    pub branch: String,
+}
+
+impl Default for GitSync {
+    fn default() -> Self {
+        GitSync { remote: None, branch: "main".to_string() }
+    }
+}
+
+/// What `sync` did.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    pub pulled: bool,
+    pub pushed: bool,
+    /// How task level conflicts were settled
+    pub notes: Vec<String>,
+}
+
+/// The git repository in the data directory. Every workspace's tasks file is
+/// tracked in it, so they all sync to the one branch.
+#[derive(Debug)]
+pub struct Repo {
+    dir: PathBuf,
+    /// This workspace's tasks file, relative to `dir`
+    file: String,
+}
+
+impl Repo {
+    /// Opens the repository in `root`, creating one on `branch` if there is
+    /// none yet, for the tasks file at `path` inside it.
+    pub fn open(root: &Path, path: &Path, branch: &str) -> Result<Repo, TaskError> {
+        let dir = if root.as_os_str().is_empty() { PathBuf::from(".") } else { root.to_path_buf() };
+        let file = relative(&dir, path)?;
+        // Merging and diffing work on the text formats only
+        let format = StoreFormat::for_path(path);
+        if matches!(format, StoreFormat::Sqlite | StoreFormat::Events) {
+            return Err(TaskError::Git(format!("git versioning needs YAML, JSON or TOML storage, {} storage can't be merged", format)));
+        }
+        let repo = Repo { dir, file };
+
+        // Older versions made one repository per workspace, which git would
+        // treat as a submodule here
+        if let Some(nested) = repo.dir.join(&repo.file).parent().filter(|d| *d != repo.dir && d.join(".git").exists()) {
+            return Err(TaskError::Git(format!(
+                "{} has its own git repository from an older version, move its .git out of the way to track it in {}",
+                nested.display(),
+                repo.dir.display()
+            )));
+        }
+
+        if !repo.dir.join(".git").exists() {
+            repo.git(&["init", "-q"])?;
+            repo.git(&["symbolic-ref", "HEAD", &format!("refs/heads/{}", branch)])?;
+            // Commits need an author, even on machines without a git identity
+            if !repo.succeeds(&["config", "user.name"])? {
+                repo.git(&["config", "user.name", "task-manager"])?;
+                repo.git(&["config", "user.email", "task-manager@localhost"])?;
+            }
+        }
+        Ok(repo)
+    }
+
+    /// Adds another tasks file in the repository to the next commit, e.g. the
+    /// workspace a task was moved to.
+    pub fn stage(&self, path: &Path) -> Result<(), TaskError> {
+        if path.exists() {
+            self.git(&["add", "--", &relative(&self.dir, path)?])?;
+        }
+        Ok(())
+    }
+
+    /// Commits the tasks file and anything staged if it changed, returning
+    /// whether it did.
+    pub fn commit(&self, message: &str) -> Result<bool, TaskError> {
+        if self.dir.join(&self.file).exists() {
+            self.git(&["add", "--", &self.file])?;
+        }
+        if self.succeeds(&["diff", "--cached", "--quiet"])? {
+            return Ok(false);
+        }
+        self.git(&["commit", "-q", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Commits local changes to every workspace, merges `branch` from `remote`
+    /// task by task and pushes the result back.
+    pub fn sync(&self, remote: &str, branch: &str) -> Result<SyncReport, TaskError> {
+        let mut report = SyncReport::default();
+        self.git(&["add", "-u"])?;
+        self.commit("sync local changes")?;
+        let has_head = self.succeeds(&["rev-parse", "--verify", "-q", "HEAD"])?;
+
+        if self.succeeds(&["ls-remote", "--exit-code", "--heads", remote, branch])? {
+            self.git(&["fetch", "-q", remote, branch])?;
+            report.pulled = true;
+
+            if !has_head {
+                self.git(&["reset", "-q", "--hard", "FETCH_HEAD"])?;
+            } else if self.succeeds(&["merge-base", "--is-ancestor", "FETCH_HEAD", "HEAD"])? {
+                // Nothing new on the remote
+            } else if self.has_file("HEAD")? && !self.has_file("FETCH_HEAD")? {
+                // Fast-forwarding or merging would delete it here
+                return Err(TaskError::Git(format!("{} on {} has no {}, not syncing with it", branch, remote, self.file)));
+            } else if self.succeeds(&["merge-base", "--is-ancestor", "HEAD", "FETCH_HEAD"])? {
+                self.git(&["merge", "-q", "--ff-only", "FETCH_HEAD"])?;
+            } else {
+                report.notes = self.merge(remote)?;
+            }
+        }
+
+        if self.succeeds(&["rev-parse", "--verify", "-q", "HEAD"])? {
+            self.git(&["push", "-q", remote, &format!("HEAD:refs/heads/{}", branch)])?;
+            report.pushed = true;
+        }
+        Ok(report)
+    }
+
+    // Records a merge with FETCH_HEAD whose tasks files are merged task by
+    // task rather than line by line
+    fn merge(&self, remote: &str) -> Result<Vec<String>, TaskError> {
+        let base = self.output(&["merge-base", "HEAD", "FETCH_HEAD"]).ok().map(|base| base.trim().to_string());
+        let mut files = self.files_at("HEAD")?;
+        for file in self.files_at("FETCH_HEAD")? {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+
+        let mut merges = Vec::new();
+        for file in &files {
+            let base = match &base {
+                Some(base) => self.tasks_at(base, file)?,
+                None => Tasks::new(),
+            };
+            merges.push(merge_tasks(&base, &self.tasks_at("HEAD", file)?, &self.tasks_at("FETCH_HEAD", file)?));
+        }
+
+        self.git(&["merge", "-q", "--no-commit", "--no-ff", "--allow-unrelated-histories", "-s", "ours", "FETCH_HEAD"])?;
+        let mut notes = Vec::new();
+        for (file, (merged, file_notes)) in files.iter().zip(merges) {
+            let path = self.dir.join(file);
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            persistence::write_atomic(&path, persistence::to_string(&merged, StoreFormat::for_path(&path))?.as_bytes())?;
+            self.git(&["add", "--", file])?;
+            // Notes on other workspaces say which one they're about
+            notes.extend(file_notes.into_iter().map(|note| if *file == self.file { note } else { format!("{}: {}", file, note) }));
+        }
+        self.git(&["commit", "-q", "-m", &format!("sync: merge {}", remote)])?;
+        Ok(notes)
+    }
+
+    // The tasks files in `rev`, those sharing this one's extension
+    fn files_at(&self, rev: &str) -> Result<Vec<String>, TaskError> {
+        let extension = Path::new(&self.file).extension();
+        Ok(self
+            .output(&["ls-tree", "-r", "--name-only", rev])?
+            .lines()
+            .filter(|file| Path::new(file).extension() == extension)
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn has_file(&self, rev: &str) -> Result<bool, TaskError> {
+        self.succeeds(&["cat-file", "-e", &format!("{}:./{}", rev, self.file)])
+    }
+
+    fn tasks_at(&self, rev: &str, file: &str) -> Result<Tasks, TaskError> {
+        match self.output(&["show", &format!("{}:./{}", rev, file)]) {
+            Ok(data) => persistence::from_str(&data, StoreFormat::for_path(Path::new(file))),
+            // The file didn't exist yet in that commit
+            Err(_) => Ok(Tasks::new()),
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Output, TaskError> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .map_err(|e| TaskError::Git(format!("can't run git: {}", e)))
+    }
+
+    fn output(&self, args: &[&str]) -> Result<String, TaskError> {
+        let output = self.run(args)?;
+        if !output.status.success() {
+            return Err(TaskError::Git(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<(), TaskError> {
+        self.output(args).map(|_| ())
+    }
+
+    // For commands whose exit status is the answer
+    fn succeeds(&self, args: &[&str]) -> Result<bool, TaskError> {
+        Ok(self.run(args)?.status.success())
+    }
+}
+
+// `path` relative to `dir`, taking `./data` and `data` to be the same
+fn relative(dir: &Path, path: &Path) -> Result<String, TaskError> {
+    let plain = |path: &Path| -> PathBuf { path.components().filter(|c| *c != Component::CurDir).collect() };
+    match plain(path).strip_prefix(plain(dir)) {
+        Ok(file) => Ok(file.to_string_lossy().to_string()),
+        Err(_) => Err(TaskError::Git(format!("{} isn't inside {}", path.display(), dir.display()))),
+    }
+}
+
+/// Three-way merge of two edited copies of `base`. Each task is merged on its
+/// own: a side that left a task alone takes the other side's version, and
+/// fields changed on both sides keep the local value. Tasks added on both
+/// sides under the same id are both kept, the local one gets a new id.
+pub fn merge_tasks(base: &Tasks, ours: &Tasks, theirs: &Tasks) -> (Tasks, Vec<String>) {
+    let find = |tasks: &Tasks, id: u32| tasks.tasks.iter().find(|t| t.id == id).cloned();
+    let mut ids: Vec<u32> = ours.tasks.iter().map(|t| t.id).collect();
+    ids.extend(theirs.tasks.iter().map(|t| t.id).filter(|id| !ids.contains(id)).collect::<Vec<_>>());
+    ids.extend(base.tasks.iter().map(|t| t.id).filter(|id| !ids.contains(id)).collect::<Vec<_>>());
+
+    let mut merged = Vec::new();
+    let mut renumber = Vec::new();
+    let mut notes = Vec::new();
+    for id in ids {
+        let (b, o, t) = (find(base, id), find(ours, id), find(theirs, id));
+        let task = match (&b, o, t) {
+            (_, o, t) if o == t => o,
+            (b, o, t) if o == *b => t,
+            (b, o, t) if t == *b => o,
+            (None, Some(o), Some(t)) => {
+                renumber.push(o);
+                Some(t)
+            }
+            (Some(b), Some(o), Some(t)) => Some(merge_fields(b, &o, &t, &mut notes)),
+            (Some(_), None, Some(t)) => {
+                notes.push(format!("kept #{} \"{}\", deleted here but changed remotely", t.id, t.name));
+                Some(t)
+            }
+            (Some(_), Some(o), None) => {
+                notes.push(format!("kept #{} \"{}\", deleted remotely but changed here", o.id, o.name));
+                Some(o)
+            }
+            (_, o, t) => o.or(t),
+        };
+        merged.extend(task);
+    }
+
+    let mut next_id = [ours.next_id, theirs.next_id, merged.iter().map(|t| t.id + 1).max().unwrap_or(1)]
+        .into_iter()
+        .max()
+        .unwrap_or(1);
+    for mut task in renumber {
+        notes.push(format!("renumbered local #{} \"{}\" to #{}, the remote added #{} too", task.id, task.name, next_id, task.id));
+        task.id = next_id;
+        next_id += 1;
+        merged.push(task);
+    }
+
+    (Tasks { tasks: merged, next_id }, notes)
+}
+
+fn merge_fields(base: &Task, ours: &Task, theirs: &Task, notes: &mut Vec<String>) -> Task {
+    let fields = |task: &Task| match serde_yaml::to_value(task) {
+        Ok(Value::Mapping(mapping)) => mapping,
+        _ => Mapping::new(),
+    };
+    let (b, o, t) = (fields(base), fields(ours), fields(theirs));
+
+    let mut keys: Vec<&Value> = Vec::new();
+    let mut seen = HashSet::new();
+    for (key, _) in o.iter().chain(t.iter()).chain(b.iter()) {
+        if seen.insert(key.as_str().unwrap_or_default()) {
+            keys.push(key);
+        }
+    }
+
+    let mut merged = Mapping::new();
+    for key in keys {
+        let (bv, ov, tv) = (b.get(key), o.get(key), t.get(key));
+        let value = if ov == tv || tv == bv {
+            ov
+        } else if ov == bv {
+            tv
+        } else {
+            notes.push(format!(
+                "#{} \"{}\": {} changed on both sides, kept the local value",
+                ours.id,
+                ours.name,
+                key.as_str().unwrap_or_default()
+            ));
+            ov
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    serde_yaml::from_value(Value::Mapping(merged)).unwrap_or_else(|_| ours.clone())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::tests_common::{task, tasks};
+
+    use super::*;
+
+    const DUE: &str = "2024-05-01T09:00:00Z";
+
+    #[test]
+    fn merges_task_by_task() {
+        let base = tasks(vec![task(1, "Write report", DUE), task(2, "Call Bob", DUE), task(3, "Old", DUE)]);
+
+        let mut ours = tasks(base.tasks.clone());
+        ours.tasks[0].name = "Write the report".to_string();
+        ours.tasks.retain(|t| t.id != 3);
+        ours.tasks.push(task(4, "Local", DUE));
+
+        let mut theirs = tasks(base.tasks.clone());
+        theirs.tasks[0].completed = true;
+        theirs.tasks[1].description = "About the venue".to_string();
+        theirs.tasks.push(task(4, "Remote", DUE));
+
+        let (merged, notes) = merge_tasks(&base, &ours, &theirs);
+
+        let summary: Vec<(u32, &str, bool, &str)> =
+            merged.tasks.iter().map(|t| (t.id, t.name.as_str(), t.completed, t.description.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "Write the report", true, ""),
+                (2, "Call Bob", false, "About the venue"),
+                (4, "Remote", false, ""),
+                (5, "Local", false, ""),
+            ]
+        );
+        assert_eq!(merged.next_id, 6);
+        assert_eq!(notes, vec!["renumbered local #4 \"Local\" to #5, the remote added #4 too"]);
+    }
+
+    #[test]
+    fn keeps_local_value_when_both_change_a_field() {
+        let base = tasks(vec![task(1, "Write report", DUE)]);
+        let mut ours = tasks(base.tasks.clone());
+        ours.tasks[0].name = "Ours".to_string();
+        let mut theirs = tasks(base.tasks.clone());
+        theirs.tasks[0].name = "Theirs".to_string();
+        theirs.tasks[0].project = Some("work".to_string());
+
+        let (merged, notes) = merge_tasks(&base, &ours, &theirs);
+
+        assert_eq!(merged.tasks[0].name, "Ours");
+        assert_eq!(merged.tasks[0].project, Some("work".to_string()));
+        assert_eq!(notes, vec!["#1 \"Ours\": name changed on both sides, kept the local value"]);
+    }
+
+    // Two data directories syncing through a local bare repo
+    #[test]
+    fn syncs_through_a_bare_remote() {
+        let root = env::temp_dir().join("git_syncs_through_a_bare_remote");
+        let _ = fs::remove_dir_all(&root);
+        let remote = root.join("remote.git");
+        fs::create_dir_all(&remote).unwrap();
+        let status = Command::new("git").args(["init", "-q", "--bare"]).arg(&remote).status().unwrap();
+        assert!(status.success());
+        let remote = remote.to_string_lossy().to_string();
+
+        let setup = |name: &str| {
+            let path = root.join(name).join("tasks.yaml");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            (path.clone(), Repo::open(path.parent().unwrap(), &path, "main").unwrap())
+        };
+        let (a_path, a) = setup("a");
+        let (b_path, b) = setup("b");
+
+        let mut shared = tasks(vec![task(1, "Write report", DUE)]);
+        persistence::save_tasks(&shared, Some(&a_path)).unwrap();
+        assert!(a.commit("add #1: Write report").unwrap());
+        assert!(!a.commit("nothing changed").unwrap());
+        assert!(a.sync(&remote, "main").unwrap().pushed);
+
+        // B starts from the remote
+        b.sync(&remote, "main").unwrap();
+        assert_eq!(persistence::load_from_file(Some(&b_path)).unwrap().tasks, shared.tasks);
+
+        // Both add a task and A completes #1
+        shared.add_task("From A".to_string(), "".to_string(), "2024-05-02T09:00:00Z".to_string());
+        shared.complete_task(1).unwrap();
+        persistence::save_tasks(&shared, Some(&a_path)).unwrap();
+        a.commit("add #2: From A").unwrap();
+        a.sync(&remote, "main").unwrap();
+
+        let mut b_tasks = persistence::load_from_file(Some(&b_path)).unwrap();
+        b_tasks.add_task("From B".to_string(), "".to_string(), "2024-05-03T09:00:00Z".to_string());
+        persistence::save_tasks(&b_tasks, Some(&b_path)).unwrap();
+        b.commit("add #2: From B").unwrap();
+
+        let report = b.sync(&remote, "main").unwrap();
+        assert!(report.pulled && report.pushed);
+        assert_eq!(report.notes.len(), 1);
+
+        a.sync(&remote, "main").unwrap();
+        for path in [&a_path, &b_path] {
+            let merged = persistence::load_from_file(Some(path)).unwrap();
+            let names: Vec<(u32, &str)> = merged.tasks.iter().map(|t| (t.id, t.name.as_str())).collect();
+            assert_eq!(names, vec![(1, "Write report"), (2, "From A"), (3, "From B")]);
+            assert!(merged.tasks[0].completed);
+        }
+
+        let log = a.output(&["log", "--format=%s"]).unwrap();
+        assert!(log.starts_with("sync: merge"));
+        assert!(log.contains("add #2: From B"));
+    }
+
+    #[test]
+    fn refuses_a_remote_without_the_tasks_file() {
+        let root = env::temp_dir().join("git_refuses_a_remote_without_the_tasks_file");
+        let _ = fs::remove_dir_all(&root);
+        let remote = root.join("remote.git");
+        fs::create_dir_all(&remote).unwrap();
+        assert!(Command::new("git").args(["init", "-q", "--bare"]).arg(&remote).status().unwrap().success());
+        let remote = remote.to_string_lossy().to_string();
+
+        let path = root.join("a").join("tasks.yaml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let repo = Repo::open(path.parent().unwrap(), &path, "main").unwrap();
+        persistence::save_tasks(&tasks(vec![task(1, "Write report", DUE)]), Some(&path)).unwrap();
+        repo.sync(&remote, "main").unwrap();
+
+        // Another clone deletes the file and pushes
+        let clone = root.join("clone");
+        let git = |args: &[&str]| assert!(Command::new("git").arg("-C").arg(&clone).args(args).status().unwrap().success());
+        assert!(Command::new("git").args(["clone", "-q", "-b", "main", &remote]).arg(&clone).status().unwrap().success());
+        git(&["rm", "-q", "tasks.yaml"]);
+        git(&["-c", "user.name=t", "-c", "user.email=t@localhost", "commit", "-q", "-m", "remove"]);
+        git(&["push", "-q", "origin", "HEAD:main"]);
+
+        assert!(matches!(repo.sync(&remote, "main"), Err(TaskError::Git(_))));
+        assert_eq!(persistence::load_from_file(Some(&path)).unwrap().tasks.len(), 1);
+    }
+
+    #[test]
+    fn rejects_stores_it_cant_merge() {
+        let dir = env::temp_dir().join("git_rejects_stores_it_cant_merge");
+        for file in ["tasks.db", "tasks.events"] {
+            assert!(matches!(Repo::open(&dir, &dir.join(file), "main"), Err(TaskError::Git(_))));
+        }
+        assert!(!dir.join(".git").exists());
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::BTreeSet, fmt, io::Write};
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc};
+use colored::Colorize;
+use serde::Deserialize;
+
+use super::{Task, TaskError};
+
+const BAR_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    #[default]
+    Week,
+    Month,
+}
+
+impl Period {
+    /// First and last day of the period containing `day`. Weeks start on Monday.
+    pub fn range(&self, day: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Period::Day => (day, day),
+            Period::Week => {
+                let start = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+                (start, start + Duration::days(6))
+            }
+            Period::Month => {
+                let start = day.with_day(1).unwrap_or(day);
+                let end = start + Months::new(1) - Duration::days(1);
+                (start, end)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Period::Day => write!(f, "day"),
+            Period::Week => write!(f, "week"),
+            Period::Month => write!(f, "month"),
+        }
+    }
+}
+
+/// A completion target, read from the `goals` list in the config file:
+///
+/// ```yaml
+/// goals:
+///   - target: 15
+///     period: week
+///   - name: Writing
+///     target: 3
+///     tag: writing
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Goal {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Tasks to complete per period
+    pub target: usize,
+    #[serde(default)]
+    pub period: Period,
+    /// Only count tasks in this project
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Only count tasks with this tag
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+impl Goal {
+    fn label(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+
+        let mut label = format!("{} tasks per {}", self.target, self.period);
+        if let Some(project) = &self.project {
+            label.push_str(&format!(" in {}", project));
+        }
+        if let Some(tag) = &self.tag {
+            label.push_str(&format!(" tagged {}", tag));
+        }
+        label
+    }
+
+    fn counts(&self, task: &Task) -> bool {
+        self.project.as_ref().is_none_or(|p| task.project.as_ref() == Some(p))
+            && self.tag.as_ref().is_none_or(|tag| task.tags.contains(tag))
+    }
+
+    /// Tasks matching the goal completed in the period containing `today`.
+    pub fn progress<Tz: TimeZone>(&self, tasks: &[Task], today: NaiveDate, tz: &Tz) -> usize {
+        let (start, end) = self.period.range(today);
+        tasks
+            .iter()
+            .filter(|t| t.completed && self.counts(t))
+            .filter_map(|t| t.completed_at)
+            .map(|at| at.with_timezone(tz).date_naive())
+            .filter(|day| *day >= start && *day <= end)
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Streaks {
+    /// Consecutive days with a completion up to today, or up to yesterday
+    /// while today has none yet
+    pub current: usize,
+    pub longest: usize,
+}
+
+/// Daily completion streaks from recorded completion times, in `tz`.
+pub fn streaks<Tz: TimeZone>(tasks: &[Task], today: NaiveDate, tz: &Tz) -> Streaks {
+    let days: BTreeSet<NaiveDate> = tasks
+        .iter()
+        .filter(|t| t.completed)
+        .filter_map(|t| t.completed_at)
+        .map(|at| at.with_timezone(tz).date_naive())
+        .collect();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in &days {
+        run = match previous {
+            Some(p) if *day - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let mut day = if days.contains(&today) { today } else { today - Duration::days(1) };
+    let mut current = 0;
+    while days.contains(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+
+    Streaks { current, longest }
+}
+
+/// Prints a progress bar per goal for the current period, then the streaks.
+pub fn write_goals<Tz: TimeZone>(
+    writer: &mut impl Write,
+    tasks: &[Task],
+    goals: &[Goal],
+    now: DateTime<Utc>,
+    tz: &Tz,
+    color: bool,
+) -> Result<(), TaskError> {
+    let today = now.with_timezone(tz).date_naive();
+
+    if goals.is_empty() {
+        writeln!(writer, "No goals set, add them under `goals:` in the config file")?;
+    }
+
+    let labels: Vec<String> = goals.iter().map(Goal::label).collect();
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    for (goal, label) in goals.iter().zip(&labels) {
+        let done = goal.progress(tasks, today, tz);
+        let target = goal.target.max(1);
+        let filled = (done.min(target) * BAR_WIDTH) / target;
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+        let bar = match (color, done >= goal.target) {
+            (true, true) => bar.green().to_string(),
+            (true, false) => bar.yellow().to_string(),
+            (false, _) => bar,
+        };
+        let percent = done * 100 / target;
+        writeln!(writer, "{:<w$}  {}  {}/{} ({}%)", label, bar, done, goal.target, percent, w = label_width)?;
+    }
+
+    let streaks = streaks(tasks, today, tz);
+    if !goals.is_empty() {
+        writeln!(writer)?;
+    }
+    writeln!(writer, "Current streak: {}", days(streaks.current))?;
+    writeln!(writer, "Longest streak: {}", days(streaks.longest))?;
+
+    Ok(())
+}
+
+fn days(count: usize) -> String {
+    if count == 1 { "1 day".to_string() } else { format!("{} days", count) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::{day, done};
+
+    use super::*;
+
+    fn goal(target: usize, period: Period) -> Goal {
+        Goal { name: None, target, period, project: None, tag: None }
+    }
+
+    #[test]
+    fn period_ranges() {
+        // 2024-05-15 is a Wednesday
+        assert_eq!(Period::Day.range(day("2024-05-15")), (day("2024-05-15"), day("2024-05-15")));
+        assert_eq!(Period::Week.range(day("2024-05-15")), (day("2024-05-13"), day("2024-05-19")));
+        assert_eq!(Period::Month.range(day("2024-02-15")), (day("2024-02-01"), day("2024-02-29")));
+    }
+
+    #[test]
+    fn counts_completions_in_the_current_period() {
+        let mut tasks = vec![
+            done(1, "2024-05-13T08:00:00Z"),
+            done(2, "2024-05-15T08:00:00Z"),
+            done(3, "2024-05-12T08:00:00Z"),
+        ];
+        tasks[1].tags = vec!["writing".to_string()];
+
+        assert_eq!(goal(15, Period::Week).progress(&tasks, day("2024-05-15"), &Utc), 2);
+        assert_eq!(goal(15, Period::Month).progress(&tasks, day("2024-05-15"), &Utc), 3);
+
+        let writing = Goal { tag: Some("writing".to_string()), ..goal(3, Period::Week) };
+        assert_eq!(writing.progress(&tasks, day("2024-05-15"), &Utc), 1);
+    }
+
+    #[test]
+    fn computes_current_and_longest_streaks() {
+        let tasks = vec![
+            done(1, "2024-05-01T08:00:00Z"),
+            done(2, "2024-05-02T08:00:00Z"),
+            done(3, "2024-05-02T09:00:00Z"),
+            done(4, "2024-05-03T08:00:00Z"),
+            done(5, "2024-05-08T08:00:00Z"),
+            done(6, "2024-05-09T08:00:00Z"),
+        ];
+
+        // Nothing done yet today, but yesterday keeps the streak alive
+        assert_eq!(streaks(&tasks, day("2024-05-10"), &Utc), Streaks { current: 2, longest: 3 });
+        assert_eq!(streaks(&tasks, day("2024-05-11"), &Utc), Streaks { current: 0, longest: 3 });
+        assert_eq!(streaks(&[], day("2024-05-11"), &Utc), Streaks { current: 0, longest: 0 });
+    }
+
+    #[test]
+    fn draws_progress_bars() {
+        let tasks = vec![done(1, "2024-05-14T08:00:00Z"), done(2, "2024-05-15T08:00:00Z")];
+        let goals = vec![goal(4, Period::Week), Goal { name: Some("Daily".to_string()), ..goal(1, Period::Day) }];
+        let now = "2024-05-15T12:00:00Z".parse().unwrap();
+
+        let mut writer = Vec::new();
+        write_goals(&mut writer, &tasks, &goals, now, &Utc, false).unwrap();
+
+        let expected = "\
+4 tasks per week  ██████████░░░░░░░░░░  2/4 (50%)
+Daily             ████████████████████  1/1 (100%)
+
+Current streak: 2 days
+Longest streak: 2 days
+";
+        assert_eq!(String::from_utf8(writer).unwrap(), expected);
+    }
+
+    #[test]
+    fn parses_goals_from_yaml() {
+        let goals: Vec<Goal> = serde_yaml::from_str("- target: 15\n- target: 2\n  period: day\n  project: work\n").unwrap();
+
+        assert_eq!(goals[0], goal(15, Period::Week));
+        assert_eq!(goals[1].period, Period::Day);
+        assert_eq!(goals[1].label(), "2 tasks per day in work");
+    }
+}
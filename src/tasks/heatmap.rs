@@ -0,0 +1,275 @@
+use std::{collections::BTreeMap, io::Write};
+
+use chrono::{Datelike, Duration, NaiveDate, TimeZone};
+use colored::Colorize;
+
+use super::{report::escape, Task, TaskError};
+
+// A year plus the partial week at either end
+pub const WEEKS: usize = 53;
+const DAY_LABEL_WIDTH: usize = 4;
+const CELL_WIDTH: usize = 2;
+
+// Plain text shading for each level, from nothing done to the busiest days
+const SHADES: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+const COLORS: [(u8, u8, u8); 5] = [(235, 237, 240), (155, 233, 168), (64, 196, 99), (48, 161, 78), (33, 110, 57)];
+
+const SVG_CELL: usize = 11;
+const SVG_GAP: usize = 2;
+const SVG_LEFT: usize = 32;
+const SVG_TOP: usize = 24;
+
+/// Completions per day for the weeks up to `today`, laid out like a
+/// contribution graph: one column per week starting on Monday.
+#[derive(Debug)]
+pub struct Heatmap {
+    /// Monday of the first column
+    pub start: NaiveDate,
+    pub today: NaiveDate,
+    pub counts: BTreeMap<NaiveDate, usize>,
+}
+
+impl Heatmap {
+    /// Counts recorded completions per day in `tz`, over `weeks` columns.
+    pub fn new<Tz: TimeZone>(tasks: &[Task], today: NaiveDate, tz: &Tz, weeks: usize) -> Heatmap {
+        let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let start = this_week - Duration::weeks(weeks.max(1) as i64 - 1);
+
+        let mut counts = BTreeMap::new();
+        for at in tasks.iter().filter(|t| t.completed).filter_map(|t| t.completed_at) {
+            let day = at.with_timezone(tz).date_naive();
+            if day >= start && day <= today {
+                *counts.entry(day).or_insert(0) += 1;
+            }
+        }
+
+        Heatmap { start, today, counts }
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    fn weeks(&self) -> usize {
+        ((self.today - self.start).num_days() / 7 + 1) as usize
+    }
+
+    fn count(&self, day: NaiveDate) -> usize {
+        self.counts.get(&day).copied().unwrap_or(0)
+    }
+
+    // 0 for no completions, otherwise 1 to 4 relative to the busiest day
+    fn level(&self, count: usize) -> usize {
+        let max = self.counts.values().copied().max().unwrap_or(0);
+        if count == 0 || max == 0 {
+            0
+        } else {
+            (count * 4).div_ceil(max).clamp(1, 4)
+        }
+    }
+
+    // Days of the grid row by row, `None` for days after today
+    fn rows(&self) -> Vec<Vec<Option<NaiveDate>>> {
+        (0..7)
+            .map(|weekday| {
+                (0..self.weeks())
+                    .map(|week| {
+                        let day = self.start + Duration::days((week * 7 + weekday) as i64);
+                        (day <= self.today).then_some(day)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Columns where a new month starts, with its short name
+    fn month_starts(&self) -> Vec<(usize, String)> {
+        let mut starts = Vec::new();
+        let mut previous = None;
+        for week in 0..self.weeks() {
+            let day = self.start + Duration::weeks(week as i64);
+            if previous != Some(day.month()) {
+                starts.push((week, day.format("%b").to_string()));
+                previous = Some(day.month());
+            }
+        }
+        starts
+    }
+
+    pub fn write_terminal(&self, writer: &mut impl Write, color: bool) -> Result<(), TaskError> {
+        let mut months = " ".repeat(DAY_LABEL_WIDTH);
+        for (week, name) in self.month_starts() {
+            let column = DAY_LABEL_WIDTH + week * CELL_WIDTH;
+            // Skip a label that would run into the previous one
+            if months.chars().count() <= column {
+                months.push_str(&" ".repeat(column - months.chars().count()));
+                months.push_str(&name);
+            }
+        }
+        writeln!(writer, "{}", months.trim_end())?;
+
+        for (weekday, row) in self.rows().iter().enumerate() {
+            let label = match weekday {
+                0 => "Mon",
+                2 => "Wed",
+                4 => "Fri",
+                _ => "",
+            };
+            let mut line = format!("{:<w$}", label, w = DAY_LABEL_WIDTH);
+            for day in row {
+                let Some(day) = day else { break };
+                line.push_str(&self.cell(self.level(self.count(*day)), color));
+                line.push(' ');
+            }
+            writeln!(writer, "{}", line.trim_end())?;
+        }
+
+        let legend: Vec<String> = (0..SHADES.len()).map(|level| self.cell(level, color)).collect();
+        writeln!(writer)?;
+        writeln!(writer, "{} completed since {}    Less {} More", self.total(), self.start.format("%b %-d, %Y"), legend.join(" "))?;
+
+        Ok(())
+    }
+
+    fn cell(&self, level: usize, color: bool) -> String {
+        if color {
+            let (r, g, b) = COLORS[level];
+            "■".truecolor(r, g, b).to_string()
+        } else {
+            SHADES[level].to_string()
+        }
+    }
+
+    /// A standalone SVG where each day's cell shows its count on hover.
+    pub fn write_svg(&self, writer: &mut impl Write) -> Result<(), TaskError> {
+        let step = SVG_CELL + SVG_GAP;
+        let width = SVG_LEFT + self.weeks() * step + SVG_GAP;
+        let height = SVG_TOP + 7 * step + 28;
+
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="10">"#,
+            w = width,
+            h = height
+        )?;
+        writeln!(writer, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+
+        for (week, name) in self.month_starts() {
+            writeln!(writer, r#"<text x="{}" y="{}">{}</text>"#, SVG_LEFT + week * step, SVG_TOP - 8, escape(&name))?;
+        }
+        for (weekday, label) in [(0, "Mon"), (2, "Wed"), (4, "Fri")] {
+            writeln!(writer, r#"<text x="0" y="{}">{}</text>"#, SVG_TOP + weekday * step + SVG_CELL - 2, label)?;
+        }
+
+        for (weekday, row) in self.rows().iter().enumerate() {
+            for (week, day) in row.iter().enumerate() {
+                let Some(day) = day else { continue };
+                let count = self.count(*day);
+                let (r, g, b) = COLORS[self.level(count)];
+                let noun = if count == 1 { "completion" } else { "completions" };
+                writeln!(
+                    writer,
+                    r#"<rect x="{}" y="{}" width="{c}" height="{c}" rx="2" fill="rgb({},{},{})"><title>{} {} on {}</title></rect>"#,
+                    SVG_LEFT + week * step,
+                    SVG_TOP + weekday * step,
+                    r,
+                    g,
+                    b,
+                    count,
+                    noun,
+                    day,
+                    c = SVG_CELL
+                )?;
+            }
+        }
+
+        writeln!(writer, r#"<text x="{}" y="{}">{} completed since {}</text>"#, SVG_LEFT, height - 8, self.total(), self.start.format("%b %-d, %Y"))?;
+        writeln!(writer, "</svg>")?;
+
+        Ok(())
+    }
+}
+
+/// Weeks that fit in `width` terminal cells, at most a year.
+pub fn weeks_for_width(width: usize) -> usize {
+    (width.saturating_sub(DAY_LABEL_WIDTH) / CELL_WIDTH).clamp(1, WEEKS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::tests_common::{day, done};
+
+    use super::*;
+
+    fn sample() -> Vec<Task> {
+        vec![
+            done(1, "2024-05-06T09:00:00Z"),
+            done(2, "2024-05-06T10:00:00Z"),
+            done(3, "2024-05-06T11:00:00Z"),
+            done(4, "2024-05-06T12:00:00Z"),
+            done(5, "2024-05-08T09:00:00Z"),
+            // Older than the grid
+            done(6, "2023-01-01T09:00:00Z"),
+        ]
+    }
+
+    #[test]
+    fn counts_completions_in_the_grid() {
+        // 2024-05-08 is a Wednesday
+        let heatmap = Heatmap::new(&sample(), day("2024-05-08"), &Utc, 2);
+
+        assert_eq!(heatmap.start, day("2024-04-29"));
+        assert_eq!(heatmap.total(), 5);
+        assert_eq!(heatmap.level(4), 4);
+        assert_eq!(heatmap.level(1), 1);
+        assert_eq!(heatmap.level(0), 0);
+    }
+
+    #[test]
+    fn draws_weeks_as_columns() {
+        let heatmap = Heatmap::new(&sample(), day("2024-05-08"), &Utc, 2);
+
+        let mut writer = Vec::new();
+        heatmap.write_terminal(&mut writer, false).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+
+        // "May" would overlap "Apr", so it is left out
+        let expected = "    Apr
+Mon · █
+    · ·
+Wed · ░
+    ·
+Fri ·
+    ·
+    ·
+
+5 completed since Apr 29, 2024    Less · ░ ▒ ▓ █ More
+";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn svg_cells_have_tooltips() {
+        let heatmap = Heatmap::new(&sample(), day("2024-05-08"), &Utc, 2);
+
+        let mut writer = Vec::new();
+        heatmap.write_svg(&mut writer).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+
+        assert!(output.starts_with("<svg"));
+        assert!(output.contains("<title>4 completions on 2024-05-06</title>"));
+        assert!(output.contains("<title>1 completion on 2024-05-08</title>"));
+        // Seven days last week and three so far this week
+        assert_eq!(output.matches("<title>").count(), 10);
+    }
+
+    #[test]
+    fn fits_weeks_to_the_terminal() {
+        assert_eq!(weeks_for_width(200), 53);
+        assert_eq!(weeks_for_width(24), 10);
+        assert_eq!(weeks_for_width(0), 1);
+    }
+}
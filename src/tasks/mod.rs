@@ -3,19 +3,46 @@ use std::error::Error;
 use serde::{Serialize, Deserialize};
 
 mod task;
+mod backup;
 mod crud;
 mod update;
 mod filtering;
 mod stats;
+mod calendar;
+mod board;
+mod chart;
+mod agenda;
+mod dates;
+mod encrypted;
+mod events;
+mod forecast;
+mod git;
+mod goals;
+mod heatmap;
+mod report;
+mod sqlite;
+mod svg;
+mod table;
+mod template;
+mod workspace;
 
 pub mod cli;
+pub mod output;
 pub mod persistence;
+pub mod store;
+pub mod tui;
 
 pub use cli::*;
 pub use task::*;
 pub use crud::*;
 pub use update::*;
 pub use stats::*;
+pub use table::{Column, terminal_width};
+pub use template::{Template, TemplateError};
+pub use goals::Goal;
+pub use backup::Retention;
+pub use git::GitSync;
+pub use workspace::Workspaces;
 
 #[derive(Debug)]
 pub enum TaskError {
@@ -26,7 +53,19 @@ pub enum TaskError {
     ParseBoolError,
     Io(std::io::Error),
     Yaml(serde_yaml::Error),
+    Json(serde_json::Error),
+    Toml(String),
+    Sqlite(rusqlite::Error),
+    /// A git command run for versioning or sync failed
+    Git(String),
+    /// The passphrase didn't decrypt the tasks file, or the file was modified
+    WrongPassphrase,
+    /// No usable passphrase, or an encrypted file that can't be read
+    Encryption(String),
+    Csv(csv::Error),
     InvalidInput(String),
+    /// Another process held the lock on the data file for too long
+    Locked { path: std::path::PathBuf, waited: std::time::Duration },
 }
 
 
@@ -40,7 +79,20 @@ impl std::fmt::Display for TaskError {
             TaskError::ParseBoolError => write!(f, "Error parsing string to boolean"),
             TaskError::Io(err) => write!(f, "IO error: {}", err),
             TaskError::Yaml(err) => write!(f, "YAML error: {}", err),
+            TaskError::Json(err) => write!(f, "JSON error: {}", err),
+            TaskError::Toml(msg) => write!(f, "TOML error: {}", msg),
+            TaskError::Sqlite(err) => write!(f, "SQLite error: {}", err),
+            TaskError::Git(msg) => write!(f, "git error: {}", msg),
+            TaskError::WrongPassphrase => write!(f, "Wrong passphrase, or the encrypted file has been modified"),
+            TaskError::Encryption(msg) => write!(f, "Encryption error: {}", msg),
+            TaskError::Csv(err) => write!(f, "CSV error: {}", err),
             TaskError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            TaskError::Locked { path, waited } => write!(
+                f,
+                "{} is locked by another task-manager process, gave up after {}s",
+                path.display(),
+                waited.as_secs()
+            ),
         } 
     } 
 } 
@@ -60,6 +112,36 @@ impl From<serde_yaml::Error> for TaskError {
     } 
 } 
 
+impl From<serde_json::Error> for TaskError {
+    fn from(err: serde_json::Error) -> Self {
+        TaskError::Json(err)
+    } 
+} 
+
+impl From<toml::de::Error> for TaskError {
+    fn from(err: toml::de::Error) -> Self {
+        TaskError::Toml(err.to_string())
+    } 
+} 
+
+impl From<toml::ser::Error> for TaskError {
+    fn from(err: toml::ser::Error) -> Self {
+        TaskError::Toml(err.to_string())
+    } 
+} 
+
+impl From<rusqlite::Error> for TaskError {
+    fn from(err: rusqlite::Error) -> Self {
+        TaskError::Sqlite(err)
+    } 
+} 
+
+impl From<csv::Error> for TaskError {
+    fn from(err: csv::Error) -> Self {
+        TaskError::Csv(err)
+    } 
+} 
+
 
 #[cfg(test)]
 mod tests {
@@ -83,7 +165,7 @@ mod tests {
         let err = TaskError::ParseBoolError;
         assert_eq!(err.to_string(), "Error parsing string to boolean");
 
-        let err = TaskError::Io(std::io::Error::new(std::io::ErrorKind::Other, "io error"));
+        let err = TaskError::Io(std::io::Error::other("io error"));
         assert_eq!(err.to_string(), "IO error: io error");
 
         let bad_yaml = "%%not_valid_yaml";
@@ -97,12 +179,18 @@ mod tests {
 
         #[test]
     fn test_task_error_from() {
-        let io_err = std::io::Error::new(std::io::ErrorKind::Other, "io error");
+        let io_err = std::io::Error::other("io error");
         assert!(matches!(TaskError::from(io_err), TaskError::Io(_)));
 
         let bad_yaml = "%%not_valid_yaml";
         let yaml_err = serde_yaml::from_str::<serde_yaml::Value>(bad_yaml).unwrap_err();
         assert!(matches!(TaskError::from(yaml_err), TaskError::Yaml(_)));
+
+        let json_err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert!(matches!(TaskError::from(json_err), TaskError::Json(_)));
+
+        let toml_err = toml::from_str::<toml::Value>("= 1").unwrap_err();
+        assert!(matches!(TaskError::from(toml_err), TaskError::Toml(_)));
     }
 
 
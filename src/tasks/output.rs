@@ -0,0 +1,610 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use chrono::SecondsFormat;
+use colored::Colorize;
+use serde::Serialize;
+
+use super::{stats, Stats, Task, TaskError, Template, table::{self, Column}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Ndjson,
+    /// Only understood by `export-report`
+    Markdown,
+    /// Only understood by `export-report`
+    Html,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "md" | "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+            OutputFormat::Markdown => write!(f, "md"),
+            OutputFormat::Html => write!(f, "html"),
+        }
+    }
+}
+
+/// Options controlling how `list`, `show` and `stats` render their results.
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// Style text output with ANSI colors
+    pub color: bool,
+    /// Columns shown by the text table
+    pub columns: Vec<Column>,
+    /// Terminal width the text table is truncated to, if any
+    pub max_width: Option<usize>,
+    /// Renders each task in text output instead of the table
+    pub template: Option<Template>,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            color: false,
+            columns: Column::defaults(),
+            max_width: None,
+            template: None,
+        }
+    }
+}
+
+/// The stable, machine-readable shape of a task.
+///
+/// This is kept separate from `Task` so the storage format can change without
+/// breaking scripts that consume `--format json|csv|ndjson`.
+#[derive(Debug, Serialize)]
+pub struct TaskRecord<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub description: &'a str,
+    pub due_date: String,
+    pub completed: bool,
+    pub project: Option<&'a str>,
+    pub tags: &'a [String],
+    pub completed_at: Option<String>,
+}
+
+impl<'a> From<&'a Task> for TaskRecord<'a> {
+    fn from(task: &'a Task) -> Self {
+        TaskRecord {
+            id: task.id,
+            name: &task.name,
+            description: &task.description,
+            due_date: task.due_date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            completed: task.completed,
+            project: task.project.as_deref(),
+            tags: &task.tags,
+            completed_at: task.completed_at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        }
+    }
+}
+
+/// CSV has no lists, so tags are joined with spaces.
+#[derive(Debug, Serialize)]
+struct TaskCsvRecord<'a> {
+    id: u32,
+    name: &'a str,
+    description: &'a str,
+    due_date: String,
+    completed: bool,
+    project: Option<&'a str>,
+    tags: String,
+    completed_at: Option<String>,
+}
+
+impl<'a> From<TaskRecord<'a>> for TaskCsvRecord<'a> {
+    fn from(record: TaskRecord<'a>) -> Self {
+        TaskCsvRecord {
+            id: record.id,
+            name: record.name,
+            description: record.description,
+            due_date: record.due_date,
+            completed: record.completed,
+            project: record.project,
+            tags: record.tags.join(" "),
+            completed_at: record.completed_at,
+        }
+    }
+}
+
+pub fn write_tasks(writer: &mut impl Write, tasks: &[Task], options: &OutputOptions) -> Result<(), TaskError> {
+    let records: Vec<TaskRecord> = tasks.iter().map(TaskRecord::from).collect();
+
+    match options.format {
+        OutputFormat::Text => match &options.template {
+            Some(template) => {
+                for task in tasks {
+                    writeln!(writer, "{}", template.render(task))?;
+                }
+            }
+            None => table::write_table(writer, tasks, &options.columns, options.max_width, options.color)?,
+        },
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, &records)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(writer, &records)?,
+        OutputFormat::Csv => {
+            let rows: Vec<TaskCsvRecord> = records.into_iter().map(TaskCsvRecord::from).collect();
+            write_csv(writer, &rows)?
+        }
+        OutputFormat::Markdown | OutputFormat::Html => return Err(unsupported(options.format)),
+    }
+
+    Ok(())
+}
+
+/// Writes the tasks of several workspaces, under a heading per workspace for
+/// text and with a `workspace` field on each record otherwise.
+pub fn write_workspace_tasks(writer: &mut impl Write, workspaces: &[(String, Vec<Task>)], options: &OutputOptions) -> Result<(), TaskError> {
+    let records: Vec<WorkspaceTaskRecord> = workspaces
+        .iter()
+        .flat_map(|(workspace, tasks)| tasks.iter().map(move |task| WorkspaceTaskRecord { workspace, task: TaskRecord::from(task) }))
+        .collect();
+
+    match options.format {
+        OutputFormat::Text => {
+            for (index, (workspace, tasks)) in workspaces.iter().enumerate() {
+                if index > 0 {
+                    writeln!(writer)?;
+                }
+                let heading = format!("{} ({} tasks)", workspace, tasks.len());
+                writeln!(writer, "{}", if options.color { heading.bold().to_string() } else { heading })?;
+                write_tasks(writer, tasks, options)?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, &records)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(writer, &records)?,
+        OutputFormat::Csv => {
+            let rows: Vec<WorkspaceTaskCsvRecord> = records.into_iter().map(WorkspaceTaskCsvRecord::from).collect();
+            write_csv(writer, &rows)?
+        }
+        OutputFormat::Markdown | OutputFormat::Html => return Err(unsupported(options.format)),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceTaskRecord<'a> {
+    workspace: &'a str,
+    #[serde(flatten)]
+    task: TaskRecord<'a>,
+}
+
+/// The csv crate can't flatten, so the workspace column is spelled out.
+#[derive(Debug, Serialize)]
+struct WorkspaceTaskCsvRecord<'a> {
+    workspace: &'a str,
+    id: u32,
+    name: &'a str,
+    description: &'a str,
+    due_date: String,
+    completed: bool,
+    project: Option<&'a str>,
+    tags: String,
+    completed_at: Option<String>,
+}
+
+impl<'a> From<WorkspaceTaskRecord<'a>> for WorkspaceTaskCsvRecord<'a> {
+    fn from(record: WorkspaceTaskRecord<'a>) -> Self {
+        let task = TaskCsvRecord::from(record.task);
+        WorkspaceTaskCsvRecord {
+            workspace: record.workspace,
+            id: task.id,
+            name: task.name,
+            description: task.description,
+            due_date: task.due_date,
+            completed: task.completed,
+            project: task.project,
+            tags: task.tags,
+            completed_at: task.completed_at,
+        }
+    }
+}
+
+pub fn write_task(writer: &mut impl Write, task: &Task, options: &OutputOptions) -> Result<(), TaskError> {
+    if let (OutputFormat::Text, Some(template)) = (options.format, &options.template) {
+        writeln!(writer, "{}", template.render(task))?;
+        return Ok(());
+    }
+
+    if options.format == OutputFormat::Csv {
+        return write_csv(writer, &[TaskCsvRecord::from(TaskRecord::from(task))]);
+    }
+
+    write_record(writer, task, &TaskRecord::from(task), options)
+}
+
+pub fn write_stats(writer: &mut impl Write, stats: &Stats, options: &OutputOptions) -> Result<(), TaskError> {
+    if options.format == OutputFormat::Csv {
+        return write_csv(writer, &[StatsCsvRecord::from(stats)]);
+    }
+
+    write_record(writer, stats, stats, options)
+}
+
+/// Writes one stats record per group, each carrying its group name.
+pub fn write_grouped_stats(writer: &mut impl Write, groups: &[(String, Stats)], options: &OutputOptions) -> Result<(), TaskError> {
+    let records: Vec<GroupStatsRecord> = groups
+        .iter()
+        .map(|(group, stats)| GroupStatsRecord { group, stats })
+        .collect();
+
+    match options.format {
+        OutputFormat::Text => stats::write_group_table(writer, groups)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, &records)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(writer, &records)?,
+        OutputFormat::Csv => {
+            let rows: Vec<GroupStatsCsvRecord> = records.iter().map(GroupStatsCsvRecord::from).collect();
+            write_csv(writer, &rows)?
+        }
+        OutputFormat::Markdown | OutputFormat::Html => return Err(unsupported(options.format)),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct GroupStatsRecord<'a> {
+    group: &'a str,
+    #[serde(flatten)]
+    stats: &'a Stats,
+}
+
+/// The csv crate can't flatten, so the group column is spelled out.
+#[derive(Debug, Serialize)]
+struct GroupStatsCsvRecord<'a> {
+    group: &'a str,
+    total: usize,
+    completed: usize,
+    percent_completed: usize,
+    overdue: usize,
+    due_today: usize,
+    due_this_week: usize,
+    average_lead_time_hours: Option<f64>,
+    median_lead_time_hours: Option<f64>,
+    completions_per_week: String,
+}
+
+impl<'a> From<&GroupStatsRecord<'a>> for GroupStatsCsvRecord<'a> {
+    fn from(record: &GroupStatsRecord<'a>) -> Self {
+        let stats = StatsCsvRecord::from(record.stats);
+        GroupStatsCsvRecord {
+            group: record.group,
+            total: stats.total,
+            completed: stats.completed,
+            percent_completed: stats.percent_completed,
+            overdue: stats.overdue,
+            due_today: stats.due_today,
+            due_this_week: stats.due_this_week,
+            average_lead_time_hours: stats.average_lead_time_hours,
+            median_lead_time_hours: stats.median_lead_time_hours,
+            completions_per_week: stats.completions_per_week,
+        }
+    }
+}
+
+/// CSV has no lists, so weekly completions are joined as `week_start=count`
+/// pairs separated by semicolons.
+#[derive(Debug, Serialize)]
+struct StatsCsvRecord {
+    total: usize,
+    completed: usize,
+    percent_completed: usize,
+    overdue: usize,
+    due_today: usize,
+    due_this_week: usize,
+    average_lead_time_hours: Option<f64>,
+    median_lead_time_hours: Option<f64>,
+    completions_per_week: String,
+}
+
+impl From<&Stats> for StatsCsvRecord {
+    fn from(stats: &Stats) -> Self {
+        let weeks: Vec<String> = stats
+            .completions_per_week
+            .iter()
+            .map(|week| format!("{}={}", week.week_start, week.completed))
+            .collect();
+
+        StatsCsvRecord {
+            total: stats.total,
+            completed: stats.completed,
+            percent_completed: stats.percent_completed,
+            overdue: stats.overdue,
+            due_today: stats.due_today,
+            due_this_week: stats.due_this_week,
+            average_lead_time_hours: stats.average_lead_time_hours,
+            median_lead_time_hours: stats.median_lead_time_hours,
+            completions_per_week: weeks.join(";"),
+        }
+    }
+}
+
+// Single values are printed as one object for json/ndjson and a header plus
+// one row for csv.
+fn write_record<D, S>(writer: &mut impl Write, value: &D, record: &S, options: &OutputOptions) -> Result<(), TaskError>
+where
+    D: fmt::Display + ?Sized,
+    S: Serialize,
+{
+    match options.format {
+        OutputFormat::Text => {
+            // Stats already end with a newline, a task does not
+            let text = value.to_string();
+            if text.ends_with('\n') {
+                write!(writer, "{}", text)?;
+            } else {
+                writeln!(writer, "{}", text)?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, record)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(writer, std::slice::from_ref(record))?,
+        OutputFormat::Csv => write_csv(writer, std::slice::from_ref(record))?,
+        OutputFormat::Markdown | OutputFormat::Html => return Err(unsupported(options.format)),
+    }
+
+    Ok(())
+}
+
+fn unsupported(format: OutputFormat) -> TaskError {
+    TaskError::InvalidInput(format!("--format {} is only supported by export-report", format))
+}
+
+fn write_ndjson<S: Serialize>(writer: &mut impl Write, records: &[S]) -> Result<(), TaskError> {
+    for record in records {
+        serde_json::to_writer(&mut *writer, record)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn write_csv<S: Serialize>(writer: &mut impl Write, records: &[S]) -> Result<(), TaskError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for record in records {
+        csv_writer.serialize(record)?;
+    }
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::create_tasks_completion;
+
+    use super::*;
+
+    fn options(format: OutputFormat) -> OutputOptions {
+        OutputOptions { format, ..Default::default() }
+    }
+
+    #[test]
+    fn parse_output_format_from_str() {
+        assert!(matches!(OutputFormat::from_str("text"), Ok(OutputFormat::Text)));
+        assert!(matches!(OutputFormat::from_str("json"), Ok(OutputFormat::Json)));
+        assert!(matches!(OutputFormat::from_str("csv"), Ok(OutputFormat::Csv)));
+        assert!(matches!(OutputFormat::from_str("ndjson"), Ok(OutputFormat::Ndjson)));
+        assert!(matches!(OutputFormat::from_str("md"), Ok(OutputFormat::Markdown)));
+        assert!(matches!(OutputFormat::from_str("html"), Ok(OutputFormat::Html)));
+        assert!(OutputFormat::from_str("xml").is_err());
+    }
+
+    #[test]
+    fn writes_tasks_as_json_array() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[0].description = "has - dashes - inside".to_string();
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks, &options(OutputFormat::Json)).unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&writer).unwrap();
+        let records = value.as_array().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["id"], 1);
+        assert_eq!(records[0]["description"], "has - dashes - inside");
+        assert_eq!(records[0]["completed"], true);
+        assert!(records[0]["due_date"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn writes_project_and_tags() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[0].project = Some("work".to_string());
+        tasks.tasks[0].tags = vec!["urgent".to_string(), "q2".to_string()];
+        tasks.tasks[0].completed_at = None;
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks[..1], &options(OutputFormat::Json)).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&writer).unwrap();
+        assert_eq!(value[0]["project"], "work");
+        assert_eq!(value[0]["tags"], serde_json::json!(["urgent", "q2"]));
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks[..1], &options(OutputFormat::Csv)).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.lines().nth(1).unwrap().ends_with(",work,urgent q2,"));
+    }
+
+    #[test]
+    fn writes_tasks_as_ndjson() {
+        let tasks = create_tasks_completion();
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks, &options(OutputFormat::Ndjson)).unwrap();
+
+        let output = String::from_utf8(writer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["name"], "Task 2");
+    }
+
+    #[test]
+    fn writes_tasks_as_csv_with_header() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[0].name = "Name, with comma".to_string();
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks, &options(OutputFormat::Csv)).unwrap();
+
+        let output = String::from_utf8(writer).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("id,name,description,due_date,completed,project,tags,completed_at"));
+        assert!(lines.next().unwrap().starts_with("1,\"Name, with comma\",Text for task1,"));
+    }
+
+    #[test]
+    fn writes_workspace_tasks_with_workspace_names() {
+        let tasks = create_tasks_completion();
+        let workspaces = vec![("default".to_string(), tasks.tasks[..1].to_vec()), ("work".to_string(), tasks.tasks[1..2].to_vec())];
+
+        let mut writer = Vec::new();
+        write_workspace_tasks(&mut writer, &workspaces, &options(OutputFormat::Csv)).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "workspace,id,name,description,due_date,completed,project,tags,completed_at");
+        assert!(lines[1].starts_with("default,1,"));
+        assert!(lines[2].starts_with("work,2,"));
+
+        let mut writer = Vec::new();
+        write_workspace_tasks(&mut writer, &workspaces, &options(OutputFormat::Text)).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.starts_with("default (1 tasks)\n"));
+        assert!(output.contains("\n\nwork (1 tasks)\n"));
+    }
+
+    #[test]
+    fn writes_stats_as_json() {
+        let tasks = create_tasks_completion();
+
+        let mut writer = Vec::new();
+        write_stats(&mut writer, &tasks.stats(), &options(OutputFormat::Json)).unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&writer).unwrap();
+        assert_eq!(value["total"], 3);
+        assert_eq!(value["completed"], 2);
+        assert_eq!(value["percent_completed"], 66);
+        assert_eq!(value["overdue"], 0);
+        assert_eq!(value["completions_per_week"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn writes_stats_as_csv() {
+        let tasks = create_tasks_completion();
+
+        let mut writer = Vec::new();
+        write_stats(&mut writer, &tasks.stats(), &options(OutputFormat::Csv)).unwrap();
+
+        let output = String::from_utf8(writer).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("total,completed,percent_completed,overdue,due_today,due_this_week,average_lead_time_hours,median_lead_time_hours,completions_per_week")
+        );
+        assert_eq!(lines.next().unwrap().split(',').nth(8).unwrap().split(';').count(), 4);
+    }
+
+    #[test]
+    fn writes_grouped_stats_with_group_names() {
+        let tasks = create_tasks_completion();
+        let groups = vec![("work".to_string(), tasks.stats()), ("home".to_string(), tasks.stats())];
+
+        let mut writer = Vec::new();
+        write_grouped_stats(&mut writer, &groups, &options(OutputFormat::Json)).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&writer).unwrap();
+        assert_eq!(value[1]["group"], "home");
+        assert_eq!(value[1]["total"], 3);
+
+        let mut writer = Vec::new();
+        write_grouped_stats(&mut writer, &groups, &options(OutputFormat::Csv)).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.starts_with("group,total,completed,"));
+        assert!(output.lines().nth(1).unwrap().starts_with("work,3,2,66,"));
+    }
+
+    #[test]
+    fn writes_tasks_as_text_table() {
+        let tasks = create_tasks_completion();
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks, &options(OutputFormat::Text)).unwrap();
+
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.starts_with("ID  Name"));
+        assert_eq!(output.lines().count(), 5);
+    }
+
+    #[test]
+    fn writes_tasks_with_template() {
+        let tasks = create_tasks_completion();
+        let options = OutputOptions {
+            template: Some(Template::parse("{id}|{?completed:x|-}|{name}").unwrap()),
+            ..Default::default()
+        };
+
+        let mut writer = Vec::new();
+        write_tasks(&mut writer, &tasks.tasks, &options).unwrap();
+
+        let output = String::from_utf8(writer).unwrap();
+        assert_eq!(output, "1|x|Task 1\n2|x|Task 2\n3|-|Task 3\n");
+    }
+
+    #[test]
+    fn rejects_report_formats() {
+        let tasks = create_tasks_completion();
+
+        let result = write_tasks(&mut Vec::new(), &tasks.tasks, &options(OutputFormat::Html));
+
+        assert!(matches!(result, Err(TaskError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn writes_single_task_as_text() {
+        let tasks = create_tasks_completion();
+
+        let mut writer = Vec::new();
+        write_task(&mut writer, &tasks.tasks[0], &options(OutputFormat::Text)).unwrap();
+
+        let output = String::from_utf8(writer).unwrap();
+        assert_eq!(output, format!("{}\n", tasks.tasks[0]));
+    }
+}
@@ -1,54 +1,371 @@
-use std::{path::Path, fs};
+use std::{fmt, fs::{self, File, TryLockError}, io::Write, path::{Path, PathBuf}, str::FromStr, thread, time::{Duration, Instant}};
 
 use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::paths;
+
+use super::{backup::{self, Backup, Retention}, Task, Tasks, TaskError};
+
+/// How long to wait for another invocation to finish with the data file
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_POLL: Duration = Duration::from_millis(50);
+
+/// Version written by this build. Files without a `version` are version 1.
+pub const SCHEMA_VERSION: u32 = 2;
+
+// Upgrades a document in place, describing each change it made
+type MigrationStep = fn(&mut Mapping) -> Vec<String>;
+
+// Steps up to the given version from the one before it, in order
+const MIGRATIONS: &[(u32, MigrationStep)] = &[
+    (2, migrate_v1_to_v2),
+];
+
+/// How the tasks file is stored. The text formats hold the same schema,
+/// SQLite keeps a table per record type (see `store::SqliteStore`) and the
+/// event log a line per change (see `store::EventStore`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreFormat {
+    #[default]
+    Yaml,
+    Json,
+    Toml,
+    Sqlite,
+    Events,
+}
+
+impl StoreFormat {
+    /// The format matching `path`'s extension, YAML for anything else.
+    pub fn for_path(path: &Path) -> StoreFormat {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => StoreFormat::Json,
+            Some("toml") => StoreFormat::Toml,
+            Some("db" | "sqlite" | "sqlite3") => StoreFormat::Sqlite,
+            Some("events") => StoreFormat::Events,
+            _ => StoreFormat::Yaml,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            StoreFormat::Yaml => "yaml",
+            StoreFormat::Json => "json",
+            StoreFormat::Toml => "toml",
+            StoreFormat::Sqlite => "db",
+            StoreFormat::Events => "events",
+        }
+    }
+
+    // Every format is read into a YAML value so migrations only deal with one
+    // document type
+    fn parse(self, data: &str) -> Result<Value, TaskError> {
+        Ok(match self {
+            StoreFormat::Yaml => serde_yaml::from_str(data)?,
+            StoreFormat::Json => serde_json::from_str(data)?,
+            StoreFormat::Toml => toml::from_str(data)?,
+            StoreFormat::Sqlite | StoreFormat::Events => return Err(not_text(self)),
+        })
+    }
+
+    fn serialize(self, schema: &TasksSchema) -> Result<String, TaskError> {
+        Ok(match self {
+            StoreFormat::Yaml => serde_yaml::to_string(schema)?,
+            StoreFormat::Json => serde_json::to_string_pretty(schema)? + "\n",
+            StoreFormat::Toml => toml::to_string(schema)?,
+            StoreFormat::Sqlite | StoreFormat::Events => return Err(not_text(self)),
+        })
+    }
+}
 
-use super::{Task, Tasks, TaskError};
+fn not_text(format: StoreFormat) -> TaskError {
+    TaskError::InvalidInput(format!("{} storage isn't a single document, open it with store::open", format))
+}
 
-pub const TASKS_FILE: &str = "./data/tasks.yaml";
+impl FromStr for StoreFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(StoreFormat::Yaml),
+            "json" => Ok(StoreFormat::Json),
+            "toml" => Ok(StoreFormat::Toml),
+            "sqlite" | "db" => Ok(StoreFormat::Sqlite),
+            "events" => Ok(StoreFormat::Events),
+            _ => Err(format!("Unknown storage format '{}', expected yaml, json, toml, sqlite or events", s)),
+        }
+    }
+}
+
+impl fmt::Display for StoreFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreFormat::Sqlite => f.write_str("sqlite"),
+            _ => f.write_str(self.extension()),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 struct TasksSchema {
+    version: u32,
+    next_id: u32,
     tasks: Vec<Task>,
 } 
 
+/// What loading an older file changes, see `plan_migration`.
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub from: u32,
+    pub to: u32,
+    pub changes: Vec<String>,
+}
+
+/// Saves the tasks in the current schema, in the format matching the file's
+/// extension. The old file is first copied into `backups/`, and a file still
+/// in an older schema is also copied to `tasks.yaml.v<N>.bak`.
 pub fn save_tasks(tasks: &Tasks, path: Option<&Path>) -> Result<(), TaskError> {
-    let path = path.unwrap_or(Path::new(TASKS_FILE));
-    write_tasks(tasks, path)?;
-    Ok(())
+    let default = paths::default_data_file();
+    let path = path.unwrap_or(&default);
+    save_in(tasks, path, StoreFormat::for_path(path), &Retention::default())
 } 
 
+/// `save_tasks` with an explicit format and backup retention.
+pub fn save_in(tasks: &Tasks, path: &Path, format: StoreFormat, retention: &Retention) -> Result<(), TaskError> {
+    let data = to_string(tasks, format)?;
+    // Read-only commands save too, an unchanged file needs no backup or write
+    if fs::read_to_string(path).is_ok_and(|current| current == data) {
+        return Ok(());
+    }
+
+    backup::create(path, retention, chrono::Utc::now())?;
+    if path.exists() {
+        let version = document_version(&read_document(path, format)?)?;
+        if version < SCHEMA_VERSION {
+            fs::copy(path, backup_path(path, version))?;
+        }
+    }
+    write_atomic(path, data.as_bytes())
+}
 
+
+/// Loads the tasks, upgrading older files in memory. `TaskError::NoFile` means
+/// there is no file yet, anything else that the file couldn't be used.
 pub fn load_from_file(task_file_path: Option<&Path>) -> Result<Tasks, TaskError> {
-    let path = task_file_path.unwrap_or(Path::new(TASKS_FILE));
-    match read_tasks(path) {
-        Ok(tasks) => Ok(tasks),
-        Err(_) => Err(TaskError::NoFile)
-    } 
+    let default = paths::default_data_file();
+    let path = task_file_path.unwrap_or(&default);
+    load_in(path, StoreFormat::for_path(path))
 } 
 
-fn write_tasks(tasks: &Tasks, path: &Path) -> Result<(), TaskError> {
-    let schema = TasksSchema {
-        tasks: tasks.tasks.clone()
-    }; 
+/// Replaces the file at `path` with the backup called `name`, once it has
+/// loaded successfully. The file being replaced is backed up first.
+pub fn restore_backup(path: &Path, format: StoreFormat, name: &str, retention: &Retention) -> Result<Backup, TaskError> {
+    let restored = backup::find(path, name)?;
+    read_tasks(&restored.path, format)
+        .map_err(|e| TaskError::InvalidInput(format!("{} can't be loaded: {}", restored.name, e)))?;
+
+    let data = fs::read(&restored.path)?;
+    backup::create(path, retention, chrono::Utc::now())?;
+    write_atomic(path, &data)?;
+    Ok(restored)
+}
+
+/// `load_from_file` with an explicit format.
+pub fn load_in(path: &Path, format: StoreFormat) -> Result<Tasks, TaskError> {
+    match read_tasks(path, format) {
+        Err(TaskError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => Err(TaskError::NoFile),
+        result => result,
+    } 
+}
 
-    let yaml = serde_yaml::to_string(&schema)?;
+/// Reports the migrations loading `path` would apply, without changing it.
+pub fn plan_migration(path: Option<&Path>) -> Result<Migration, TaskError> {
+    let default = paths::default_data_file();
+    let path = path.unwrap_or(&default);
+    plan_migration_in(path, StoreFormat::for_path(path))
+}
 
-    fs::write(path, yaml)?;
+/// `plan_migration` with an explicit format.
+pub fn plan_migration_in(path: &Path, format: StoreFormat) -> Result<Migration, TaskError> {
+    let document = read_document(path, format)?;
+    let from = document_version(&document)?;
+    let (_, changes) = migrate(document)?;
 
-    Ok(())
-} 
+    Ok(Migration { from, to: SCHEMA_VERSION, changes })
+}
+
+/// Where `save_tasks` keeps a copy of a version `version` file.
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    sibling(path, &format!(".v{}.bak", version))
+}
 
-fn read_tasks(path: &Path) -> Result<Tasks, TaskError> {
+fn read_document(path: &Path, format: StoreFormat) -> Result<Value, TaskError> {
     let data = fs::read_to_string(path)?;
+    format.parse(&data)
+}
+
+fn document_version(document: &Value) -> Result<u32, TaskError> {
+    let version = match document.get("version") {
+        None => 1,
+        Some(value) => value
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| TaskError::InvalidInput(format!("unreadable schema version {:?}", value)))?,
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(TaskError::InvalidInput(format!(
+            "the tasks file has schema version {}, but this build only understands up to {}; upgrade task-manager",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    Ok(version)
+}
+
+// Applies each migration newer than the document's version, collecting a
+// description of what changed
+fn migrate(mut document: Value) -> Result<(Value, Vec<String>), TaskError> {
+    let version = document_version(&document)?;
+    let mapping = document
+        .as_mapping_mut()
+        .ok_or_else(|| TaskError::InvalidInput("the tasks file is not a mapping".to_string()))?;
+
+    let mut changes = Vec::new();
+    for (to, step) in MIGRATIONS.iter().filter(|(to, _)| *to > version) {
+        for change in step(mapping) {
+            changes.push(format!("v{}: {}", to, change));
+        }
+        mapping.insert(Value::from("version"), Value::from(*to));
+    }
+
+    Ok((document, changes))
+}
+
+// Version 1 had no stored next_id and derived it from the number of tasks, so
+// ids were reused after deletes. Duplicates get fresh ids.
+fn migrate_v1_to_v2(document: &mut Mapping) -> Vec<String> {
+    let mut changes = Vec::new();
+    let id_key = Value::from("id");
+    let tasks = document
+        .get_mut(&Value::from("tasks"))
+        .and_then(Value::as_sequence_mut);
+
+    let mut next_id = 1;
+    if let Some(tasks) = tasks {
+        let ids: Vec<u64> = tasks.iter().filter_map(|t| t.get("id").and_then(Value::as_u64)).collect();
+        next_id = ids.iter().max().map_or(1, |max| max + 1);
+
+        let mut seen = std::collections::HashSet::new();
+        for task in tasks.iter_mut().filter_map(Value::as_mapping_mut) {
+            let Some(id) = task.get(&id_key).and_then(Value::as_u64) else { continue };
+            if !seen.insert(id) {
+                let name = task.get(&Value::from("name")).and_then(Value::as_str).unwrap_or("").to_string();
+                changes.push(format!("renumber duplicate task #{} \"{}\" to #{}", id, name, next_id));
+                task.insert(id_key.clone(), Value::from(next_id));
+                seen.insert(next_id);
+                next_id += 1;
+            }
+        }
+    }
+
+    changes.push(format!("store next_id = {}", next_id));
+    document.insert(Value::from("next_id"), Value::from(next_id));
+    changes
+}
+
+/// An advisory lock on the data file, released when dropped.
+///
+/// Held from loading until saving so two invocations can't both read the old
+/// tasks and overwrite each other's changes.
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: Option<File>,
+}
+
+/// Locks the data file at `path` (or the default), waiting up to `timeout` for
+/// other processes to release it. No lock is taken when the data directory
+/// doesn't exist, since there is then nothing to load or save.
+pub fn lock(path: Option<&Path>, timeout: Duration) -> Result<StoreLock, TaskError> {
+    let default = paths::default_data_file();
+    let path = path.unwrap_or(&default);
+    let lock_path = sibling(path, ".lock");
+    if !lock_path.parent().is_some_and(|dir| dir.as_os_str().is_empty() || dir.is_dir()) {
+        return Ok(StoreLock { _file: None });
+    }
+
+    let file = File::options().create(true).truncate(false).write(true).open(&lock_path)?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(StoreLock { _file: Some(file) }),
+            Err(TryLockError::WouldBlock) if started.elapsed() < timeout => thread::sleep(LOCK_POLL),
+            Err(TryLockError::WouldBlock) => return Err(TaskError::Locked { path: path.to_path_buf(), waited: timeout }),
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+    }
+}
+
+// `tasks.yaml` -> `tasks.yaml<suffix>` in the same directory
+pub(crate) fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
 
-    let schema: TasksSchema = serde_yaml::from_str(&data)?;
+fn write_tasks(tasks: &Tasks, path: &Path, format: StoreFormat) -> Result<(), TaskError> {
+    write_atomic(path, to_string(tasks, format)?.as_bytes())
+} 
+
+/// The file contents `save_in` would write for `tasks`.
+pub fn to_string(tasks: &Tasks, format: StoreFormat) -> Result<String, TaskError> {
+    let schema = TasksSchema {
+        version: SCHEMA_VERSION,
+        next_id: tasks.next_id,
+        tasks: tasks.tasks.clone()
+    }; 
 
-    let next_id = schema.tasks.len() as u32;
+    format.serialize(&schema)
+}
+
+/// Reads tasks from the contents of a tasks file, upgrading older schemas.
+pub fn from_str(data: &str, format: StoreFormat) -> Result<Tasks, TaskError> {
+    let (document, _) = migrate(format.parse(data)?)?;
+
+    let schema: TasksSchema = serde_yaml::from_value(document)?;
 
     Ok(Tasks {
         tasks: schema.tasks,
-        next_id
+        next_id: schema.next_id,
     })
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so a
+/// crash leaves either the old or the new file, never a truncated one.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), TaskError> {
+    let tmp_path = sibling(path, &format!(".tmp-{}", std::process::id()));
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if let Err(err) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err.into());
+    }
+
+    // Persist the rename itself, not supported on every platform
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+
+    Ok(())
+} 
+
+fn read_tasks(path: &Path, format: StoreFormat) -> Result<Tasks, TaskError> {
+    from_str(&fs::read_to_string(path)?, format)
 } 
 
 #[cfg(test)]
@@ -71,7 +388,7 @@ mod tests {
         let due_date = Utc::now().to_string();
         tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), due_date); 
 
-        write_tasks(&tasks, &tmp_file).unwrap();
+        write_tasks(&tasks, &tmp_file, StoreFormat::Yaml).unwrap();
 
         let data = fs::read_to_string(tmp_file).unwrap();
         let saved_tasks: TasksSchema = serde_yaml::from_str(&data).unwrap();
@@ -91,9 +408,9 @@ mod tests {
         tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), due_date); 
 
         // Write tasks
-        write_tasks(&tasks, &tmp_file).unwrap();
+        write_tasks(&tasks, &tmp_file, StoreFormat::Yaml).unwrap();
 
-        let tasks = read_tasks(&tmp_file).unwrap();
+        let tasks = read_tasks(&tmp_file, StoreFormat::Yaml).unwrap();
 
         assert_eq!(tasks.tasks.len(), 1);
         assert_eq!(tasks.tasks[0].description, "Text for task1");
@@ -109,7 +426,7 @@ mod tests {
         let due_date = Utc::now().to_string();
         tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), due_date); 
 
-        write_tasks(&tasks, &tmp_file).unwrap();
+        write_tasks(&tasks, &tmp_file, StoreFormat::Yaml).unwrap();
 
         let tasks = load_from_file(Some(&tmp_file)).unwrap();
 
@@ -137,12 +454,244 @@ mod tests {
         assert_eq!(saved_tasks.tasks.len(), 1);
     } 
 
+    #[test]
+    fn test_write_tasks_replaces_file_atomically() {
+        let mut tasks = Tasks::new();
+        let tmp_dir = env::temp_dir().join("test_write_tasks_atomically");
+        let _ = fs::remove_dir_all(&tmp_dir);
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let tmp_file = tmp_dir.join("tasks.yaml");
+        fs::write(&tmp_file, "old contents").unwrap();
+
+        tasks.add_task("Task 1".to_string(), "Text for task1".to_string(), Utc::now().to_string());
+        write_tasks(&tasks, &tmp_file, StoreFormat::Yaml).unwrap();
+
+        assert_eq!(read_tasks(&tmp_file, StoreFormat::Yaml).unwrap().tasks.len(), 1);
+        // Only the data file is left behind
+        let entries: Vec<_> = fs::read_dir(&tmp_dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    } 
+
+    #[test]
+    fn test_write_tasks_missing_dir_leaves_nothing() {
+        let tasks = Tasks::new();
+        let tmp_file = env::temp_dir().join("test_missing_dir").join("tasks.yaml");
+
+        assert!(write_tasks(&tasks, &tmp_file, StoreFormat::Yaml).is_err());
+        assert!(!tmp_file.exists());
+    } 
+
+    #[test]
+    fn test_lock_waits_for_holder() {
+        let tmp_file = env::temp_dir().join("test_lock_waits_for_holder.yaml");
+
+        let held = lock(Some(&tmp_file), LOCK_TIMEOUT).unwrap();
+        let result = lock(Some(&tmp_file), Duration::from_millis(100));
+        assert!(matches!(result, Err(TaskError::Locked { .. })));
+        assert!(result.unwrap_err().to_string().contains("locked by another task-manager process"));
+
+        drop(held);
+        assert!(lock(Some(&tmp_file), Duration::from_millis(100)).is_ok());
+    } 
+
+    #[test]
+    fn test_lock_without_data_dir() {
+        let tmp_file = env::temp_dir().join("test_lock_no_dir").join("tasks.yaml");
+
+        assert!(lock(Some(&tmp_file), LOCK_TIMEOUT).is_ok());
+        assert!(!tmp_file.parent().unwrap().exists());
+    } 
+
+    const V1_FILE: &str = "\
+tasks:
+- id: 1
+  name: First
+  description: ''
+  due_date: 2024-05-01T09:00:00+00:00
+  completed: false
+- id: 1
+  name: Reused id
+  description: ''
+  due_date: 2024-05-02T09:00:00+00:00
+  completed: true
+";
+
+    #[test]
+    fn test_migrates_v1_files_on_load() {
+        let tmp_file = env::temp_dir().join("test_migrates_v1_files_on_load.yaml");
+        fs::write(&tmp_file, V1_FILE).unwrap();
+
+        let tasks = load_from_file(Some(&tmp_file)).unwrap();
+
+        let ids: Vec<u32> = tasks.tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(tasks.next_id, 3);
+        // Loading alone leaves the file untouched
+        assert_eq!(fs::read_to_string(&tmp_file).unwrap(), V1_FILE);
+    } 
+
+    #[test]
+    fn test_plan_migration_reports_changes() {
+        let tmp_file = env::temp_dir().join("test_plan_migration.yaml");
+        fs::write(&tmp_file, V1_FILE).unwrap();
+
+        let migration = plan_migration(Some(&tmp_file)).unwrap();
+
+        assert_eq!(migration.from, 1);
+        assert_eq!(migration.to, SCHEMA_VERSION);
+        assert_eq!(migration.changes, vec![
+            "v2: renumber duplicate task #1 \"Reused id\" to #2".to_string(),
+            "v2: store next_id = 3".to_string(),
+        ]);
+    } 
+
+    #[test]
+    fn test_save_backs_up_older_files() {
+        let tmp_dir = env::temp_dir().join("test_save_backs_up_older_files");
+        let _ = fs::remove_dir_all(&tmp_dir);
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let tmp_file = tmp_dir.join("tasks.yaml");
+        fs::write(&tmp_file, V1_FILE).unwrap();
+
+        let tasks = load_from_file(Some(&tmp_file)).unwrap();
+        save_tasks(&tasks, Some(&tmp_file)).unwrap();
+
+        assert_eq!(fs::read_to_string(backup_path(&tmp_file, 1)).unwrap(), V1_FILE);
+        let saved = fs::read_to_string(&tmp_file).unwrap();
+        assert!(saved.starts_with("---\nversion: 2\nnext_id: 3\n"));
+        assert!(plan_migration(Some(&tmp_file)).unwrap().changes.is_empty());
+
+        // Saving a current file makes no further version backups
+        save_tasks(&tasks, Some(&tmp_file)).unwrap();
+        let files = fs::read_dir(&tmp_dir).unwrap().filter(|e| e.as_ref().unwrap().path() != backup::dir(&tmp_file));
+        assert_eq!(files.count(), 2);
+    } 
+
+    #[test]
+    fn test_rejects_newer_versions() {
+        let tmp_file = env::temp_dir().join("test_rejects_newer_versions.yaml");
+        fs::write(&tmp_file, "version: 99\nnext_id: 1\ntasks: []\n").unwrap();
+
+        let result = load_from_file(Some(&tmp_file));
+
+        assert!(result.unwrap_err().to_string().contains("schema version 99"));
+    } 
+
+    #[test]
+    fn test_load_missing_file() {
+        let tmp_file = env::temp_dir().join("test_load_missing_file.yaml");
+
+        assert!(matches!(load_from_file(Some(&tmp_file)), Err(TaskError::NoFile)));
+    } 
+
+    #[test]
+    fn test_format_for_path() {
+        assert_eq!(StoreFormat::for_path(Path::new("data/tasks.json")), StoreFormat::Json);
+        assert_eq!(StoreFormat::for_path(Path::new("data/tasks.TOML")), StoreFormat::Toml);
+        assert_eq!(StoreFormat::for_path(Path::new("data/tasks.yml")), StoreFormat::Yaml);
+        assert_eq!(StoreFormat::for_path(Path::new("data/tasks")), StoreFormat::Yaml);
+        assert_eq!(StoreFormat::for_path(Path::new("data/tasks.db")), StoreFormat::Sqlite);
+        assert_eq!("json".parse::<StoreFormat>(), Ok(StoreFormat::Json));
+        assert!("xml".parse::<StoreFormat>().is_err());
+    } 
+
+    #[test]
+    fn test_json_and_toml_round_trip() {
+        let mut tasks = Tasks::new();
+        tasks.add_task("Task 1".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        tasks.add_task("Task 2".to_string(), "Second".to_string(), "2024-05-02T09:00:00Z".to_string());
+        tasks.tasks[1].project = Some("Home".to_string());
+        tasks.tasks[1].tags = vec!["errand".to_string()];
+
+        for format in [StoreFormat::Json, StoreFormat::Toml] {
+            let tmp_file = env::temp_dir().join(format!("test_round_trip.{}", format.extension()));
+            save_tasks(&tasks, Some(&tmp_file)).unwrap();
+
+            let loaded = load_from_file(Some(&tmp_file)).unwrap();
+            assert_eq!(loaded.tasks, tasks.tasks, "{}", format);
+            assert_eq!(loaded.next_id, tasks.next_id);
+        }
+    } 
+
+    #[test]
+    fn test_migrates_json_files() {
+        let tmp_file = env::temp_dir().join("test_migrates_json_files.json");
+        let v1 = r#"{"tasks": [{"id": 1, "name": "A", "description": "", "due_date": "2024-05-01 09:00:00 UTC", "completed": false}]}"#;
+        fs::write(&tmp_file, v1).unwrap();
+
+        let tasks = load_from_file(Some(&tmp_file)).unwrap();
+
+        assert_eq!(tasks.next_id, 2);
+        assert_eq!(plan_migration(Some(&tmp_file)).unwrap().from, 1);
+    } 
+
+    #[test]
+    fn test_save_keeps_backups() {
+        let tmp_dir = env::temp_dir().join("test_save_keeps_backups");
+        let _ = fs::remove_dir_all(&tmp_dir);
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let tmp_file = tmp_dir.join("tasks.yaml");
+
+        let mut tasks = Tasks::new();
+        save_tasks(&tasks, Some(&tmp_file)).unwrap();
+        // Nothing to back up before the first save
+        assert!(backup::list(&tmp_file).unwrap().is_empty());
+
+        tasks.add_task("Task 1".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        save_tasks(&tasks, Some(&tmp_file)).unwrap();
+
+        let backups = backup::list(&tmp_file).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert!(load_from_file(Some(&backups[0].path)).unwrap().tasks.is_empty());
+    } 
+
+    #[test]
+    fn test_unchanged_save_takes_no_backup() {
+        let tmp_dir = env::temp_dir().join("test_unchanged_save_takes_no_backup");
+        let _ = fs::remove_dir_all(&tmp_dir);
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let tmp_file = tmp_dir.join("tasks.yaml");
+
+        let mut tasks = Tasks::new();
+        tasks.add_task("Task 1".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        save_tasks(&tasks, Some(&tmp_file)).unwrap();
+        // What every read-only command does after loading
+        for _ in 0..3 {
+            save_tasks(&load_from_file(Some(&tmp_file)).unwrap(), Some(&tmp_file)).unwrap();
+        }
+
+        assert!(backup::list(&tmp_file).unwrap().is_empty());
+    } 
+
+    #[test]
+    fn test_restore_backup() {
+        let tmp_dir = env::temp_dir().join("test_restore_backup");
+        let _ = fs::remove_dir_all(&tmp_dir);
+        fs::create_dir_all(tmp_dir.join("backups")).unwrap();
+        let tmp_file = tmp_dir.join("tasks.yaml");
+        let retention = Retention::default();
+
+        let mut tasks = Tasks::new();
+        tasks.add_task("Task 1".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        save_tasks(&tasks, Some(&tmp_file)).unwrap();
+        fs::copy(&tmp_file, tmp_dir.join("backups/tasks-20240501-090000.000.yaml")).unwrap();
+        fs::write(tmp_dir.join("backups/tasks-20240502-090000.000.yaml"), "tasks: [oops").unwrap();
+        save_tasks(&Tasks::new(), Some(&tmp_file)).unwrap();
+
+        let result = restore_backup(&tmp_file, StoreFormat::Yaml, "tasks-20240502-090000.000.yaml", &retention);
+        assert!(result.unwrap_err().to_string().contains("can't be loaded"));
+        assert!(load_from_file(Some(&tmp_file)).unwrap().tasks.is_empty());
+
+        restore_backup(&tmp_file, StoreFormat::Yaml, "tasks-20240501-090000.000.yaml", &retention).unwrap();
+        assert_eq!(load_from_file(Some(&tmp_file)).unwrap().tasks.len(), 1);
+    } 
+
     #[test]
     fn test_read_invalid_file() {
         let tmp_dir = env::temp_dir();
         let invalid_path = tmp_dir.join("invalid.yml");
 
-        let result = read_tasks(&invalid_path);
-        assert!(matches!(result, Err(_)));
+        let result = read_tasks(&invalid_path, StoreFormat::Yaml);
+        assert!(result.is_err());
     } 
 }
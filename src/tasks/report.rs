@@ -0,0 +1,294 @@
+use std::io::Write;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::{dates, output::OutputFormat, Stats, Task, TaskError};
+
+/// The sections of a status report, computed once and rendered as Markdown or HTML.
+pub struct Report<'a> {
+    pub since: DateTime<Utc>,
+    pub now: DateTime<Utc>,
+    pub stats: Stats,
+    pub completed: Vec<&'a Task>,
+    pub overdue: Vec<&'a Task>,
+    /// Open tasks that are not overdue, by how soon they are due
+    pub buckets: Vec<(&'static str, Vec<&'a Task>)>,
+    /// Completed tasks with no recorded completion time
+    pub undated: usize,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(tasks: &'a [Task], since: DateTime<Utc>, now: DateTime<Utc>) -> Report<'a> {
+        let mut completed: Vec<&Task> = tasks
+            .iter()
+            .filter(|t| t.completed_at.is_some_and(|at| at >= since && at <= now))
+            .collect();
+        completed.sort_by_key(|t| t.completed_at);
+        let undated = tasks.iter().filter(|t| t.completed && t.completed_at.is_none()).count();
+
+        let mut open: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+        open.sort_by_key(|t| (t.due_date, t.id));
+
+        let overdue = open.iter().copied().filter(|t| t.due_date < now).collect();
+        let end_of_today = (now.date_naive() + Duration::days(1)).and_hms_opt(0, 0, 0).expect("midnight exists").and_utc();
+        let end_of_week = now + Duration::days(7);
+
+        let bucket = |from: DateTime<Utc>, to: Option<DateTime<Utc>>| -> Vec<&Task> {
+            open.iter()
+                .copied()
+                .filter(|t| t.due_date >= from && to.is_none_or(|to| t.due_date < to))
+                .collect()
+        };
+        let buckets = vec![
+            ("Due today", bucket(now, Some(end_of_today))),
+            ("Due this week", bucket(end_of_today.max(now), Some(end_of_week))),
+            ("Later", bucket(end_of_week.max(end_of_today), None)),
+        ];
+
+        Report { since, now, stats: Stats::new(tasks), completed, overdue, buckets, undated }
+    }
+
+    /// Writes the report as Markdown (also for the default text format) or HTML.
+    pub fn write(&self, writer: &mut impl Write, format: OutputFormat) -> Result<(), TaskError> {
+        match format {
+            OutputFormat::Text | OutputFormat::Markdown => self.write_markdown(writer),
+            OutputFormat::Html => self.write_html(writer),
+            other => Err(TaskError::InvalidInput(format!("Reports can't be written as {}, use md or html", other))),
+        }
+    }
+
+    fn period(&self) -> String {
+        format!("{} to {}", self.since.format("%Y-%m-%d"), self.now.format("%Y-%m-%d"))
+    }
+
+    fn summary(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Total tasks", self.stats.total.to_string()),
+            ("Completed", self.stats.completed.to_string()),
+            ("Percent completed", format!("{}%", self.stats.percent_completed)),
+            ("Completed this period", self.completed.len().to_string()),
+            ("Overdue", self.overdue.len().to_string()),
+        ]
+    }
+
+    fn completed_line(task: &Task) -> String {
+        let at = task.completed_at.map(|at| at.format("%a %b %-d").to_string()).unwrap_or_default();
+        format!("#{} {} (completed {})", task.id, task.name, at)
+    }
+
+    fn open_line(&self, task: &Task) -> String {
+        format!(
+            "#{} {} (due {}, {})",
+            task.id,
+            task.name,
+            task.due_date.format("%a %b %-d"),
+            dates::relative(task.due_date, self.now)
+        )
+    }
+
+    fn write_markdown(&self, writer: &mut impl Write) -> Result<(), TaskError> {
+        writeln!(writer, "# Task report")?;
+        writeln!(writer)?;
+        writeln!(writer, "Period: {}", self.period())?;
+        writeln!(writer)?;
+
+        writeln!(writer, "## Summary")?;
+        writeln!(writer)?;
+        writeln!(writer, "| Metric | Value |")?;
+        writeln!(writer, "| --- | ---: |")?;
+        for (label, value) in self.summary() {
+            writeln!(writer, "| {} | {} |", label, value)?;
+        }
+
+        let completed: Vec<String> = self.completed.iter().map(|t| Self::completed_line(t)).collect();
+        markdown_section(writer, "##", "Completed this period", &completed)?;
+        if self.undated > 0 {
+            writeln!(writer)?;
+            writeln!(writer, "_{} completed tasks have no completion date and are not included._", self.undated)?;
+        }
+
+        let overdue: Vec<String> = self.overdue.iter().map(|t| self.open_line(t)).collect();
+        markdown_section(writer, "##", "Overdue", &overdue)?;
+
+        writeln!(writer)?;
+        writeln!(writer, "## Open by due date")?;
+        for (title, tasks) in &self.buckets {
+            let lines: Vec<String> = tasks.iter().map(|t| self.open_line(t)).collect();
+            markdown_section(writer, "###", title, &lines)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_html(&self, writer: &mut impl Write) -> Result<(), TaskError> {
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, "<html lang=\"en\">")?;
+        writeln!(writer, "<head>")?;
+        writeln!(writer, "<meta charset=\"utf-8\">")?;
+        writeln!(writer, "<title>Task report {}</title>", escape(&self.period()))?;
+        writeln!(writer, "<style>{}</style>", STYLE)?;
+        writeln!(writer, "</head>")?;
+        writeln!(writer, "<body>")?;
+        writeln!(writer, "<h1>Task report</h1>")?;
+        writeln!(writer, "<p class=\"period\">Period: {}</p>", escape(&self.period()))?;
+
+        writeln!(writer, "<h2>Summary</h2>")?;
+        writeln!(writer, "<table>")?;
+        for (label, value) in self.summary() {
+            writeln!(writer, "<tr><th>{}</th><td>{}</td></tr>", label, escape(&value))?;
+        }
+        writeln!(writer, "</table>")?;
+
+        let completed: Vec<String> = self.completed.iter().map(|t| Self::completed_line(t)).collect();
+        html_section(writer, "h2", "Completed this period", &completed, "done")?;
+        if self.undated > 0 {
+            writeln!(writer, "<p class=\"note\">{} completed tasks have no completion date and are not included.</p>", self.undated)?;
+        }
+
+        let overdue: Vec<String> = self.overdue.iter().map(|t| self.open_line(t)).collect();
+        html_section(writer, "h2", "Overdue", &overdue, "overdue")?;
+
+        writeln!(writer, "<h2>Open by due date</h2>")?;
+        for (title, tasks) in &self.buckets {
+            let lines: Vec<String> = tasks.iter().map(|t| self.open_line(t)).collect();
+            html_section(writer, "h3", title, &lines, "open")?;
+        }
+
+        writeln!(writer, "</body>")?;
+        writeln!(writer, "</html>")?;
+
+        Ok(())
+    }
+}
+
+const STYLE: &str = "body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem;color:#222}\
+h1{border-bottom:2px solid #ddd;padding-bottom:.3rem}\
+table{border-collapse:collapse}th,td{padding:.25rem .75rem;border-bottom:1px solid #eee;text-align:left}td{text-align:right}\
+ul{padding-left:1.25rem}li.overdue{color:#b00020}li.done{color:#2e7d32}\
+.period,.note,.empty{color:#666}";
+
+fn markdown_section(writer: &mut impl Write, level: &str, title: &str, lines: &[String]) -> Result<(), TaskError> {
+    writeln!(writer)?;
+    writeln!(writer, "{} {} ({})", level, title, lines.len())?;
+    writeln!(writer)?;
+    if lines.is_empty() {
+        writeln!(writer, "_None_")?;
+    }
+    for line in lines {
+        writeln!(writer, "- {}", line)?;
+    }
+    Ok(())
+}
+
+fn html_section(writer: &mut impl Write, tag: &str, title: &str, lines: &[String], class: &str) -> Result<(), TaskError> {
+    writeln!(writer, "<{tag}>{} ({})</{tag}>", escape(title), lines.len(), tag = tag)?;
+    if lines.is_empty() {
+        writeln!(writer, "<p class=\"empty\">None</p>")?;
+        return Ok(());
+    }
+    writeln!(writer, "<ul>")?;
+    for line in lines {
+        writeln!(writer, "<li class=\"{}\">{}</li>", class, escape(line))?;
+    }
+    writeln!(writer, "</ul>")?;
+    Ok(())
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::{task, TaskFixture};
+
+    use super::*;
+
+    fn sample() -> Vec<Task> {
+        vec![
+            task(1, "Ship <release>", "2024-05-09T09:00:00Z").done_at("2024-05-08T10:00:00Z"),
+            task(2, "Old win", "2024-04-01T09:00:00Z").done_at("2024-04-01T10:00:00Z"),
+            task(3, "Late report", "2024-05-08T09:00:00Z"),
+            task(4, "Standup", "2024-05-10T15:00:00Z"),
+            task(5, "Review", "2024-05-14T10:00:00Z"),
+            task(6, "Planning", "2024-06-01T10:00:00Z"),
+        ]
+    }
+
+    fn render(format: OutputFormat) -> String {
+        let tasks = sample();
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        let report = Report::new(&tasks, now - Duration::days(7), now);
+        let mut writer = Vec::new();
+        report.write(&mut writer, format).unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn buckets_tasks() {
+        let tasks = sample();
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        let report = Report::new(&tasks, now - Duration::days(7), now);
+
+        let ids = |tasks: &[&Task]| tasks.iter().map(|t| t.id).collect::<Vec<u32>>();
+        assert_eq!(ids(&report.completed), vec![1]);
+        assert_eq!(ids(&report.overdue), vec![3]);
+        assert_eq!(ids(&report.buckets[0].1), vec![4]);
+        assert_eq!(ids(&report.buckets[1].1), vec![5]);
+        assert_eq!(ids(&report.buckets[2].1), vec![6]);
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let output = render(OutputFormat::Markdown);
+
+        assert!(output.starts_with("# Task report\n\nPeriod: 2024-05-03 to 2024-05-10\n"));
+        assert!(output.contains("| Total tasks | 6 |"));
+        assert!(output.contains("| Completed this period | 1 |"));
+        assert!(output.contains("## Completed this period (1)\n\n- #1 Ship <release> (completed Wed May 8)"));
+        assert!(output.contains("## Overdue (1)\n\n- #3 Late report (due Wed May 8, 2 days late)"));
+        assert!(output.contains("### Due today (1)\n\n- #4 Standup"));
+        assert!(output.contains("### Later (1)"));
+    }
+
+    #[test]
+    fn renders_self_contained_html() {
+        let output = render(OutputFormat::Html);
+
+        assert!(output.starts_with("<!DOCTYPE html>"));
+        assert!(output.contains("<style>"));
+        assert!(output.contains("Ship &lt;release&gt;"));
+        assert!(!output.contains("<release>"));
+        assert!(!output.contains("http"));
+        assert!(!output.contains("<link"));
+        assert!(!output.contains("<script"));
+        assert!(output.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn rejects_data_formats() {
+        let tasks = sample();
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        let report = Report::new(&tasks, now - Duration::days(7), now);
+
+        assert!(report.write(&mut Vec::new(), OutputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn notes_completed_tasks_without_dates() {
+        let mut tasks = sample();
+        tasks[2].completed = true;
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        let report = Report::new(&tasks, now - Duration::days(7), now);
+
+        let mut writer = Vec::new();
+        report.write(&mut writer, OutputFormat::Markdown).unwrap();
+
+        assert!(String::from_utf8(writer).unwrap().contains("_1 completed tasks have no completion date"));
+    }
+}
@@ -0,0 +1,391 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension, Row};
+
+use super::{
+    filtering::{CompletionFilter, DueFilter, TaskQuery},
+    persistence::{Migration, SCHEMA_VERSION},
+    store::TaskStore,
+    Task, Tasks, TaskError,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        due_date TEXT NOT NULL,
+        completed INTEGER NOT NULL,
+        project TEXT,
+        created_at TEXT,
+        completed_at TEXT
+    );
+    CREATE TABLE IF NOT EXISTS task_tags (
+        task_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (task_id, position)
+    );
+    CREATE INDEX IF NOT EXISTS tasks_due_date ON tasks (due_date);
+    CREATE INDEX IF NOT EXISTS tasks_completed ON tasks (completed, due_date);
+    CREATE INDEX IF NOT EXISTS tasks_project ON tasks (project);
+    CREATE INDEX IF NOT EXISTS task_tags_tag ON task_tags (tag, task_id);
+";
+
+const TASK_COLUMNS: &str = "id, name, description, due_date, completed, project, created_at, completed_at";
+
+/// Tasks in an SQLite database file, one row per task with indexes on due
+/// date, status, project and tags so filtered queries don't scan every task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqliteStore {
+    pub path: PathBuf,
+    // The rows as of the last load or save, which the next save diffs against
+    stored: RefCell<Option<HashMap<u32, Task>>>,
+}
+
+impl SqliteStore {
+    pub fn new(path: impl Into<PathBuf>) -> SqliteStore {
+        SqliteStore { path: path.into(), stored: RefCell::new(None) }
+    }
+
+    // Opens an existing database, `NoFile` when there isn't one yet
+    fn open_existing(&self) -> Result<Connection, TaskError> {
+        if !self.path.exists() {
+            return Err(TaskError::NoFile);
+        }
+        let conn = Connection::open(&self.path)?;
+        check_version(&conn)?;
+        Ok(conn)
+    }
+
+    fn open_or_create(&self) -> Result<Connection, TaskError> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(SCHEMA)?;
+        check_version(&conn)?;
+        Ok(conn)
+    }
+}
+
+impl TaskStore for SqliteStore {
+    fn load(&self) -> Result<Tasks, TaskError> {
+        let conn = self.open_existing()?;
+        let tasks = select_tasks(&conn, "", &[])?;
+        let next_id = match meta(&conn, "next_id")? {
+            Some(next_id) => next_id,
+            None => tasks.iter().map(|t| t.id + 1).max().unwrap_or(1),
+        };
+        self.stored.replace(Some(by_id(&tasks)));
+
+        Ok(Tasks { tasks, next_id })
+    }
+
+    // Only rows that changed since the last load or save are written, so a
+    // command touching one task neither reads nor rewrites all of them. The
+    // caller's lock keeps other processes from changing rows in between.
+    fn save(&self, tasks: &Tasks) -> Result<(), TaskError> {
+        let mut conn = self.open_or_create()?;
+        // Taken, so a failed save diffs against the database next time
+        let stored = match self.stored.take() {
+            Some(stored) => stored,
+            None => by_id(&select_tasks(&conn, "", &[])?),
+        };
+
+        let tx = conn.transaction()?;
+        {
+            let mut delete_task = tx.prepare("DELETE FROM tasks WHERE id = ?1")?;
+            let mut delete_tags = tx.prepare("DELETE FROM task_tags WHERE task_id = ?1")?;
+            let mut insert_task = tx.prepare(&format!("INSERT OR REPLACE INTO tasks ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", TASK_COLUMNS))?;
+            let mut insert_tag = tx.prepare("INSERT INTO task_tags (task_id, position, tag) VALUES (?1, ?2, ?3)")?;
+
+            let kept: HashSet<u32> = tasks.tasks.iter().map(|t| t.id).collect();
+            for id in stored.keys().filter(|id| !kept.contains(id)) {
+                delete_task.execute([id])?;
+                delete_tags.execute([id])?;
+            }
+
+            for task in tasks.tasks.iter().filter(|t| stored.get(&t.id) != Some(t)) {
+                insert_task.execute(params![
+                    task.id,
+                    task.name,
+                    task.description,
+                    timestamp(task.due_date),
+                    task.completed,
+                    task.project,
+                    task.created_at.map(timestamp),
+                    task.completed_at.map(timestamp),
+                ])?;
+                delete_tags.execute([task.id])?;
+                for (position, tag) in task.tags.iter().enumerate() {
+                    insert_tag.execute(params![task.id, position, tag])?;
+                }
+            }
+        }
+        set_meta(&tx, "version", SCHEMA_VERSION)?;
+        set_meta(&tx, "next_id", tasks.next_id)?;
+        tx.commit()?;
+        self.stored.replace(Some(by_id(&tasks.tasks)));
+
+        Ok(())
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn plan_migration(&self) -> Result<Migration, TaskError> {
+        let conn = self.open_existing()?;
+        let from = meta(&conn, "version")?.unwrap_or(SCHEMA_VERSION);
+        Ok(Migration { from, to: SCHEMA_VERSION, changes: Vec::new() })
+    }
+
+    fn query(&self, query: &TaskQuery) -> Option<Result<Vec<Task>, TaskError>> {
+        Some(self.open_existing().and_then(|conn| {
+            let (clause, values) = where_clause(query, Utc::now());
+            select_tasks(&conn, &clause, &values)
+        }))
+    }
+
+    fn filters(&self) -> bool {
+        true
+    }
+}
+
+// Builds the WHERE clause for `query`, matching what `TaskQuery::filter`
+// keeps. Due dates are stored as fixed width UTC timestamps, so comparing the
+// text compares the times and can use the index.
+fn where_clause(query: &TaskQuery, now: DateTime<Utc>) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+
+    match query.due.unwrap_or(&DueFilter::All) {
+        DueFilter::PastDue => {
+            conditions.push("due_date < ?");
+            values.push(Value::Text(timestamp(now)));
+        }
+        DueFilter::DueToday => {
+            let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+            conditions.push("due_date >= ? AND due_date < ?");
+            values.push(Value::Text(timestamp(today)));
+            values.push(Value::Text(timestamp(today + Duration::days(1))));
+        }
+        DueFilter::DueThisWeek => {
+            conditions.push("due_date >= ? AND due_date <= ?");
+            values.push(Value::Text(timestamp(now)));
+            values.push(Value::Text(timestamp(now + Duration::days(7))));
+        }
+        DueFilter::All => {}
+    }
+
+    match query.status.unwrap_or(&CompletionFilter::All) {
+        CompletionFilter::Complete => conditions.push("completed = 1"),
+        CompletionFilter::Incomplete => conditions.push("completed = 0"),
+        CompletionFilter::All => {}
+    }
+
+    if let Some(project) = query.project {
+        conditions.push("project = ?");
+        values.push(Value::Text(project.to_string()));
+    }
+    if let Some(tag) = query.tag {
+        conditions.push("id IN (SELECT task_id FROM task_tags WHERE tag = ?)");
+        values.push(Value::Text(tag.to_string()));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), values)
+    }
+}
+
+fn select_tasks(conn: &Connection, clause: &str, values: &[Value]) -> Result<Vec<Task>, TaskError> {
+    let mut statement = conn.prepare(&format!("SELECT {} FROM tasks {} ORDER BY id", TASK_COLUMNS, clause))?;
+    let mut tasks = statement
+        .query_map(params_from_iter(values), task_from_row)?
+        .collect::<Result<Vec<Task>, _>>()?;
+
+    // Only the tags of the tasks selected, with the same clause and values
+    let mut tags: HashMap<u32, Vec<String>> = HashMap::new();
+    let mut statement = conn.prepare(&format!(
+        "SELECT task_id, tag FROM task_tags WHERE task_id IN (SELECT id FROM tasks {}) ORDER BY task_id, position",
+        clause
+    ))?;
+    for row in statement.query_map(params_from_iter(values), |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))? {
+        let (id, tag) = row?;
+        tags.entry(id).or_default().push(tag);
+    }
+    for task in &mut tasks {
+        task.tags = tags.remove(&task.id).unwrap_or_default();
+    }
+
+    Ok(tasks)
+}
+
+fn by_id(tasks: &[Task]) -> HashMap<u32, Task> {
+    tasks.iter().map(|t| (t.id, t.clone())).collect()
+}
+
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        due_date: parse_timestamp(row, 3)?,
+        completed: row.get(4)?,
+        project: row.get(5)?,
+        tags: Vec::new(),
+        created_at: row.get::<_, Option<String>>(6)?.map(|_| parse_timestamp(row, 6)).transpose()?,
+        completed_at: row.get::<_, Option<String>>(7)?.map(|_| parse_timestamp(row, 7)).transpose()?,
+    })
+}
+
+fn parse_timestamp(row: &Row, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let text: String = row.get(index)?;
+    text.parse()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+// Fixed width so timestamps sort and compare as text
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn meta(conn: &Connection, key: &str) -> Result<Option<u32>, TaskError> {
+    let value = conn
+        .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+        .optional();
+    match value {
+        Ok(value) => Ok(value),
+        // An empty database without the schema yet
+        Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.starts_with("no such table") => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn set_meta(conn: &Connection, key: &str, value: u32) -> Result<(), TaskError> {
+    conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+    Ok(())
+}
+
+fn check_version(conn: &Connection) -> Result<(), TaskError> {
+    match meta(conn, "version")? {
+        Some(version) if version > SCHEMA_VERSION => Err(TaskError::InvalidInput(format!(
+            "the tasks database has schema version {}, but this build only understands up to {}; upgrade task-manager",
+            version, SCHEMA_VERSION
+        ))),
+        _ => Ok(()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn store(name: &str) -> SqliteStore {
+        let path = env::temp_dir().join(format!("{}.db", name));
+        let _ = fs::remove_file(&path);
+        SqliteStore::new(path)
+    }
+
+    fn sample() -> Tasks {
+        let mut tasks = Tasks::new();
+        let now = Utc::now();
+        for (name, days) in [("Past", -2), ("Today", 0), ("Soon", 3), ("Later", 30)] {
+            let index = tasks.add_task(name.to_string(), "".to_string(), (now + Duration::days(days) + Duration::minutes(1)).to_rfc3339());
+            tasks.tasks[index].created_at = Some(now);
+        }
+        tasks.tasks[0].completed = true;
+        tasks.tasks[0].completed_at = Some(now);
+        tasks.tasks[1].project = Some("work".to_string());
+        tasks.tasks[2].project = Some("work".to_string());
+        tasks.tasks[2].tags = vec!["urgent".to_string(), "call".to_string()];
+        tasks
+    }
+
+    #[test]
+    fn round_trips_tasks() {
+        let store = store("sqlite_round_trips_tasks");
+        assert!(matches!(store.load(), Err(TaskError::NoFile)));
+
+        let tasks = sample();
+        store.save(&tasks).unwrap();
+        let loaded = store.load().unwrap();
+
+        assert_eq!(loaded.tasks, tasks.tasks);
+        assert_eq!(loaded.next_id, tasks.next_id);
+        assert_eq!(store.plan_migration().unwrap().from, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn saves_updates_and_deletes() {
+        let store = store("sqlite_saves_updates_and_deletes");
+        let mut tasks = sample();
+        store.save(&tasks).unwrap();
+
+        tasks.tasks[2].tags = vec!["call".to_string()];
+        tasks.tasks.remove(0);
+        store.save(&tasks).unwrap();
+        let loaded = store.load().unwrap();
+
+        assert_eq!(loaded.tasks, tasks.tasks);
+        assert_eq!(loaded.next_id, 5);
+    }
+
+    #[test]
+    fn saves_only_rows_changed_since_loading() {
+        let store = store("sqlite_saves_only_rows_changed_since_loading");
+        store.save(&sample()).unwrap();
+        let reopened = SqliteStore::new(&store.path);
+        let mut tasks = reopened.load().unwrap();
+
+        // Unchanged since the load, so the save below leaves the row alone
+        store.open_existing().unwrap().execute("UPDATE tasks SET name = 'Edited' WHERE id = 1", []).unwrap();
+        tasks.tasks[1].name = "Renamed".to_string();
+        reopened.save(&tasks).unwrap();
+
+        let names: Vec<String> = store.load().unwrap().tasks.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Edited", "Renamed", "Soon", "Later"]);
+    }
+
+    #[test]
+    fn filters_in_sql_like_in_memory() {
+        let store = store("sqlite_filters_in_sql_like_in_memory");
+        let tasks = sample();
+        store.save(&tasks).unwrap();
+
+        let queries = [
+            TaskQuery { due: Some(&DueFilter::PastDue), ..Default::default() },
+            TaskQuery { due: Some(&DueFilter::DueThisWeek), status: Some(&CompletionFilter::Incomplete), ..Default::default() },
+            TaskQuery { status: Some(&CompletionFilter::Complete), ..Default::default() },
+            TaskQuery { project: Some("work"), ..Default::default() },
+            TaskQuery { project: Some("work"), tag: Some("urgent"), ..Default::default() },
+            TaskQuery::default(),
+        ];
+        for query in &queries {
+            let found = store.query(query).unwrap().unwrap();
+            assert_eq!(found, query.filter(&tasks.tasks), "{:?}", query);
+        }
+    }
+
+    #[test]
+    fn uses_the_indexes() {
+        let store = store("sqlite_uses_the_indexes");
+        store.save(&sample()).unwrap();
+        let conn = store.open_existing().unwrap();
+
+        let (clause, values) = where_clause(&TaskQuery { due: Some(&DueFilter::PastDue), ..Default::default() }, Utc::now());
+        let plan: String = conn
+            .query_row(&format!("EXPLAIN QUERY PLAN SELECT id FROM tasks {}", clause), params_from_iter(&values), |row| row.get(3))
+            .unwrap();
+
+        assert!(plan.contains("tasks_due_date"), "{}", plan);
+    }
+}
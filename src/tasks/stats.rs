@@ -1,17 +1,48 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::io::Write;
+use std::str::FromStr;
 
-use crate::tasks::{Task, Tasks};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Debug)]
+use crate::tasks::{Task, TaskError, Tasks};
+
+/// Weeks of completion history included by `Stats::new`.
+pub const DEFAULT_WEEKS: u32 = 4;
+
+#[derive(Debug, Serialize)]
 pub struct Stats {
-    total: usize, 
-    completed: usize, 
-    percent_completed: usize,
+    pub total: usize, 
+    pub completed: usize, 
+    pub percent_completed: usize,
+    /// Open tasks whose due date has passed
+    pub overdue: usize,
+    /// Open tasks due on the current (UTC) day
+    pub due_today: usize,
+    /// Open tasks due within the next seven days
+    pub due_this_week: usize,
+    /// Hours from creation to completion, over tasks where both are known
+    pub average_lead_time_hours: Option<f64>,
+    pub median_lead_time_hours: Option<f64>,
+    /// Oldest week first, weeks start on Monday
+    pub completions_per_week: Vec<WeekCompletions>,
+} 
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WeekCompletions {
+    pub week_start: NaiveDate,
+    pub completed: usize,
 } 
 
 impl Stats {
     pub fn new(tasks: &[Task]) -> Stats {
+        Stats::at(tasks, Utc::now(), DEFAULT_WEEKS)
+    } 
+
+    /// Computes stats as of `now`, with completion counts for the last `weeks` weeks.
+    pub fn at(tasks: &[Task], now: DateTime<Utc>, weeks: u32) -> Stats {
         let total = tasks.len();
 
         let completed = tasks
@@ -27,10 +58,222 @@ impl Stats {
 
         };
 
-        println!("percent: {}", percent);
+        let open: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+        let overdue = open.iter().filter(|t| t.due_date < now).count();
+        let due_today = open.iter().filter(|t| t.due_date.date_naive() == now.date_naive()).count();
+        let week_end = now + Duration::days(7);
+        let due_this_week = open.iter().filter(|t| t.due_date >= now && t.due_date <= week_end).count();
+
+        let mut lead_times: Vec<f64> = tasks
+            .iter()
+            .filter_map(|t| match (t.created_at, t.completed_at) {
+                (Some(created), Some(done)) if t.completed => Some((done - created).num_seconds() as f64 / 3600.0),
+                _ => None,
+            })
+            .collect();
+        lead_times.sort_by(|a, b| a.total_cmp(b));
+
+        let average_lead_time_hours = if lead_times.is_empty() {
+            None
+        } else {
+            Some(round(lead_times.iter().sum::<f64>() / lead_times.len() as f64))
+        };
+        let median_lead_time_hours = match lead_times.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(round(lead_times[n / 2])),
+            n => Some(round((lead_times[n / 2 - 1] + lead_times[n / 2]) / 2.0)),
+        };
+
+        Stats {
+            total,
+            completed,
+            percent_completed: percent,
+            overdue,
+            due_today,
+            due_this_week,
+            average_lead_time_hours,
+            median_lead_time_hours,
+            completions_per_week: completions_per_week(tasks, now, weeks),
+        }
+    } 
+} 
+
+fn completions_per_week(tasks: &[Task], now: DateTime<Utc>, weeks: u32) -> Vec<WeekCompletions> {
+    let today = now.date_naive();
+    let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+
+    (0..weeks as i64)
+        .rev()
+        .map(|ago| {
+            let week_start = this_week - Duration::weeks(ago);
+            let week_end = week_start + Duration::weeks(1);
+            let completed = tasks
+                .iter()
+                .filter_map(|t| t.completed_at.filter(|_| t.completed))
+                .filter(|at| at.date_naive() >= week_start && at.date_naive() < week_end)
+                .count();
+            WeekCompletions { week_start, completed }
+        })
+        .collect()
+} 
+
+fn round(hours: f64) -> f64 {
+    (hours * 10.0).round() / 10.0
+} 
+
+fn format_hours(hours: Option<f64>) -> String {
+    match hours {
+        None => "n/a".to_string(),
+        Some(h) if h >= 48.0 => format!("{:.1} days", h / 24.0),
+        Some(h) => format!("{:.1} hours", h),
+    } 
+} 
+
+/// How `tasks stats --group-by` splits tasks into rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsGroup {
+    Project,
+    Tag,
+    /// Past due, today, this week or later, regardless of status
+    DueBucket,
+} 
+
+impl FromStr for StatsGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "project" => Ok(StatsGroup::Project),
+            "tag" => Ok(StatsGroup::Tag),
+            "due-bucket" | "due" => Ok(StatsGroup::DueBucket),
+            _ => Err(format!("Unknown stats grouping: {}", s)),
+        } 
+    }
+} 
+
+impl fmt::Display for StatsGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatsGroup::Project => write!(f, "project"),
+            StatsGroup::Tag => write!(f, "tag"),
+            StatsGroup::DueBucket => write!(f, "due-bucket"),
+        }
+    }
+} 
+
+/// Stats for each group, in a stable order. Tasks without a project or tag
+/// are collected last under "No project" or "Untagged", and a task with
+/// several tags counts towards each of them.
+pub fn grouped(tasks: &[Task], group: StatsGroup, now: DateTime<Utc>, weeks: u32) -> Vec<(String, Stats)> {
+    let mut groups: BTreeMap<String, Vec<Task>> = BTreeMap::new();
+    let mut rest = Vec::new();
+
+    let rest_name = match group {
+        StatsGroup::Project => "No project",
+        StatsGroup::Tag => "Untagged",
+        StatsGroup::DueBucket => {
+            let buckets = ["Past due", "Today", "This week", "Later"];
+            let mut by_bucket: Vec<Vec<Task>> = vec![Vec::new(); buckets.len()];
+            for task in tasks {
+                by_bucket[due_bucket(task, now)].push(task.clone());
+            } 
+            return buckets
+                .iter()
+                .zip(by_bucket)
+                .filter(|(_, tasks)| !tasks.is_empty())
+                .map(|(name, tasks)| (name.to_string(), Stats::at(&tasks, now, weeks)))
+                .collect();
+        } 
+    };
+
+    for task in tasks {
+        match group {
+            StatsGroup::Project => match &task.project {
+                Some(project) => groups.entry(project.clone()).or_default().push(task.clone()),
+                None => rest.push(task.clone()),
+            },
+            _ => {
+                if task.tags.is_empty() {
+                    rest.push(task.clone());
+                } 
+                for tag in &task.tags {
+                    groups.entry(tag.clone()).or_default().push(task.clone());
+                } 
+            } 
+        } 
+    } 
+
+    let mut rows: Vec<(String, Stats)> = groups
+        .into_iter()
+        .map(|(name, tasks)| (name, Stats::at(&tasks, now, weeks)))
+        .collect();
+    if !rest.is_empty() {
+        rows.push((rest_name.to_string(), Stats::at(&rest, now, weeks)));
+    } 
+    rows
+} 
+
+fn due_bucket(task: &Task, now: DateTime<Utc>) -> usize {
+    if task.due_date < now {
+        0
+    } else if task.due_date.date_naive() == now.date_naive() {
+        1
+    } else if task.due_date <= now + Duration::days(7) {
+        2
+    } else {
+        3
+    } 
+} 
+
+/// Prints one aligned row per group with the headline numbers.
+pub fn write_group_table(writer: &mut impl Write, groups: &[(String, Stats)]) -> Result<(), TaskError> {
+    if groups.is_empty() {
+        writeln!(writer, "No tasks")?;
+        return Ok(());
+    } 
+
+    let header = ["Group", "Total", "Done", "%", "Overdue", "Today", "Week", "Avg lead", "Median lead"];
+    let rows: Vec<Vec<String>> = groups
+        .iter()
+        .map(|(name, stats)| {
+            vec![
+                name.clone(),
+                stats.total.to_string(),
+                stats.completed.to_string(),
+                stats.percent_completed.to_string(),
+                stats.overdue.to_string(),
+                stats.due_today.to_string(),
+                stats.due_this_week.to_string(),
+                format_hours(stats.average_lead_time_hours),
+                format_hours(stats.median_lead_time_hours),
+            ]
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| rows.iter().map(|row| row[i].width()).chain(std::iter::once(header[i].width())).max().unwrap_or(0))
+        .collect();
 
-        Stats { total, completed, percent_completed: percent }
+    let line = |cells: Vec<&str>| -> String {
+        let formatted: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                let padding = " ".repeat(width - cell.width());
+                // Group names read left to right, numbers line up on the right
+                if i == 0 { format!("{}{}", cell, padding) } else { format!("{}{}", padding, cell) }
+            })
+            .collect();
+        formatted.join("  ").trim_end().to_string()
+    };
+
+    writeln!(writer, "{}", line(header.to_vec()))?;
+    for row in &rows {
+        writeln!(writer, "{}", line(row.iter().map(String::as_str).collect()))?;
     } 
+
+    Ok(())
 } 
 
 impl fmt::Display for Stats {
@@ -38,6 +281,18 @@ impl fmt::Display for Stats {
         writeln!(f, "Total: {}", self.total)?;
         writeln!(f, "Completed: {}", self.completed)?;
         writeln!(f, "Percent completed: {}", self.percent_completed)?;
+        writeln!(f, "Overdue: {}", self.overdue)?;
+        writeln!(f, "Due today: {}", self.due_today)?;
+        writeln!(f, "Due this week: {}", self.due_this_week)?;
+        writeln!(f, "Average lead time: {}", format_hours(self.average_lead_time_hours))?;
+        writeln!(f, "Median lead time: {}", format_hours(self.median_lead_time_hours))?;
+
+        if !self.completions_per_week.is_empty() {
+            writeln!(f, "Completions per week:")?;
+            for week in &self.completions_per_week {
+                writeln!(f, "  {}  {:>3} {}", week.week_start, week.completed, "■".repeat(week.completed))?;
+            } 
+        } 
 
         Ok(())
     }
@@ -59,3 +314,135 @@ fn test_stats() {
     assert_eq!(stats.percent_completed, 66);
 } 
 
+
+#[cfg(test)]
+use crate::tests_common::{task, TaskFixture};
+
+#[test]
+fn test_stats_due_counts() {
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let tasks = vec![
+        task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-05-01T09:00:00Z"),
+        task(2, "Task 2", "2024-05-10T09:00:00Z").created("2024-05-01T09:00:00Z"),
+        task(3, "Task 3", "2024-05-10T18:00:00Z").created("2024-05-01T09:00:00Z"),
+        task(4, "Task 4", "2024-05-14T09:00:00Z").created("2024-05-01T09:00:00Z"),
+        task(5, "Task 5", "2024-05-10T18:00:00Z").created("2024-05-01T09:00:00Z").done_at("2024-05-09T09:00:00Z"),
+    ];
+
+    let stats = Stats::at(&tasks, now, 4);
+
+    assert_eq!(stats.overdue, 2);
+    assert_eq!(stats.due_today, 2);
+    assert_eq!(stats.due_this_week, 2);
+} 
+
+#[test]
+fn test_stats_lead_times() {
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let tasks = vec![
+        task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z").done_at("2024-05-01T10:00:00Z"),
+        task(2, "Task 2", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z").done_at("2024-05-02T00:00:00Z"),
+        task(3, "Task 3", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z").done_at("2024-05-04T02:00:00Z"),
+        task(4, "Task 4", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z"),
+    ];
+
+    let stats = Stats::at(&tasks, now, 4);
+
+    assert_eq!(stats.average_lead_time_hours, Some(36.0));
+    assert_eq!(stats.median_lead_time_hours, Some(24.0));
+
+    let empty = Stats::at(&[], now, 4);
+    assert_eq!(empty.average_lead_time_hours, None);
+    assert_eq!(empty.median_lead_time_hours, None);
+} 
+
+#[test]
+fn test_stats_completions_per_week() {
+    // A Friday, so the current week started on Monday the 6th
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let tasks = vec![
+        task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-04-01T00:00:00Z").done_at("2024-05-06T00:30:00Z"),
+        task(2, "Task 2", "2024-05-08T09:00:00Z").created("2024-04-01T00:00:00Z").done_at("2024-05-05T23:30:00Z"),
+        task(3, "Task 3", "2024-05-08T09:00:00Z").created("2024-04-01T00:00:00Z").done_at("2024-04-29T08:00:00Z"),
+        task(4, "Task 4", "2024-05-08T09:00:00Z").created("2024-04-01T00:00:00Z").done_at("2024-03-01T08:00:00Z"),
+    ];
+
+    let stats = Stats::at(&tasks, now, 3);
+    let counts: Vec<(String, usize)> = stats
+        .completions_per_week
+        .iter()
+        .map(|w| (w.week_start.to_string(), w.completed))
+        .collect();
+
+    assert_eq!(counts, vec![
+        ("2024-04-22".to_string(), 0),
+        ("2024-04-29".to_string(), 2),
+        ("2024-05-06".to_string(), 1),
+    ]);
+} 
+
+#[test]
+fn test_stats_display() {
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let tasks = vec![
+        task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z").done_at("2024-05-07T00:00:00Z"),
+    ];
+
+    let output = Stats::at(&tasks, now, 1).to_string();
+
+    assert!(output.contains("Total: 1\n"));
+    assert!(output.contains("Average lead time: 6.0 days\n"));
+    assert!(output.contains("Completions per week:\n  2024-05-06    1 ■\n"));
+} 
+
+#[test]
+fn test_stats_grouped_by_project_and_tag() {
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let mut tasks = vec![
+        task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z"),
+        task(2, "Task 2", "2024-05-12T09:00:00Z").created("2024-05-01T00:00:00Z").done_at("2024-05-02T00:00:00Z"),
+        task(3, "Task 3", "2024-06-12T09:00:00Z").created("2024-05-01T00:00:00Z"),
+    ];
+    tasks[0].project = Some("work".to_string());
+    tasks[1].project = Some("work".to_string());
+    tasks[0].tags = vec!["a".to_string(), "b".to_string()];
+
+    let rows = grouped(&tasks, StatsGroup::Project, now, 4);
+    let summary: Vec<(&str, usize, usize)> = rows.iter().map(|(n, s)| (n.as_str(), s.total, s.completed)).collect();
+    assert_eq!(summary, vec![("work", 2, 1), ("No project", 1, 0)]);
+
+    let rows = grouped(&tasks, StatsGroup::Tag, now, 4);
+    let summary: Vec<(&str, usize)> = rows.iter().map(|(n, s)| (n.as_str(), s.total)).collect();
+    assert_eq!(summary, vec![("a", 1), ("b", 1), ("Untagged", 2)]);
+} 
+
+#[test]
+fn test_stats_grouped_by_due_bucket() {
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let tasks = vec![
+        task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z"),
+        task(2, "Task 2", "2024-05-12T09:00:00Z").created("2024-05-01T00:00:00Z"),
+        task(3, "Task 3", "2024-06-12T09:00:00Z").created("2024-05-01T00:00:00Z"),
+        task(4, "Task 4", "2024-06-13T09:00:00Z").created("2024-05-01T00:00:00Z"),
+    ];
+
+    let rows = grouped(&tasks, StatsGroup::DueBucket, now, 4);
+    let summary: Vec<(&str, usize)> = rows.iter().map(|(n, s)| (n.as_str(), s.total)).collect();
+
+    assert_eq!(summary, vec![("Past due", 1), ("This week", 1), ("Later", 2)]);
+    assert!(StatsGroup::from_str("owner").is_err());
+} 
+
+#[test]
+fn test_stats_group_table() {
+    let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+    let tasks = vec![task(1, "Task 1", "2024-05-08T09:00:00Z").created("2024-05-01T00:00:00Z")];
+
+    let mut writer = Vec::new();
+    write_group_table(&mut writer, &grouped(&tasks, StatsGroup::Project, now, 4)).unwrap();
+    let output = String::from_utf8(writer).unwrap();
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("Group       Total  Done  %  Overdue"));
+    assert!(lines[1].starts_with("No project      1     0  0        1"));
+} 
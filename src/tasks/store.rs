@@ -0,0 +1,169 @@
+use std::{fmt::Debug, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Utc};
+
+use crate::paths;
+
+use super::{backup::{Backup, Retention}, encrypted, filtering::TaskQuery, persistence::{self, Migration, StoreFormat}, Task, Tasks, TaskError};
+
+pub use super::encrypted::{EncryptedStore, Passphrase};
+pub use super::events::EventStore;
+pub use super::sqlite::SqliteStore;
+
+/// Where the tasks are kept. Commands load the tasks through a store and hand
+/// them back to it to be saved, without knowing how they're encoded.
+pub trait TaskStore: Debug {
+    /// Loads every task. `TaskError::NoFile` means nothing has been saved yet.
+    fn load(&self) -> Result<Tasks, TaskError>;
+
+    /// Replaces the stored tasks with `tasks`.
+    fn save(&self, tasks: &Tasks) -> Result<(), TaskError>;
+
+    /// The file backing the store, also used for locking.
+    fn path(&self) -> &Path;
+
+    /// The schema upgrades the next save would apply.
+    fn plan_migration(&self) -> Result<Migration, TaskError>;
+
+    /// Tasks matching `query`, for stores that can filter without loading
+    /// everything. `None` means the caller should filter loaded tasks itself.
+    fn query(&self, query: &TaskQuery) -> Option<Result<Vec<Task>, TaskError>> {
+        None
+    }
+
+    /// Whether `query` filters in the store, so listing needn't `load` first.
+    fn filters(&self) -> bool {
+        false
+    }
+
+    /// The tasks as they were at `at`, for stores that keep history.
+    fn as_of(&self, at: DateTime<Utc>) -> Option<Result<Tasks, TaskError>> {
+        None
+    }
+
+    /// Folds history before `before` (or all of it) into a snapshot, returning
+    /// how many changes were folded. `None` for stores without history.
+    fn compact(&self, before: Option<DateTime<Utc>>) -> Option<Result<usize, TaskError>> {
+        None
+    }
+
+    /// Swaps in the backup called `name`, for stores that keep backups.
+    fn restore(&self, name: &str) -> Option<Result<Backup, TaskError>> {
+        None
+    }
+
+    /// Rewrites the store encrypted with `passphrase`, for plain text files,
+    /// returning how many plain copies of it were encrypted along with it.
+    fn encrypt(&self, tasks: &Tasks, passphrase: Passphrase) -> Option<Result<usize, TaskError>> {
+        None
+    }
+
+    /// Rewrites the store as plain text, for encrypted files.
+    fn decrypt(&self, tasks: &Tasks) -> Option<Result<(), TaskError>> {
+        None
+    }
+}
+
+/// A single file holding the whole schema as YAML, JSON or TOML, copied into
+/// `backups/` before each save.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStore {
+    pub path: PathBuf,
+    pub format: StoreFormat,
+    pub retention: Retention,
+}
+
+impl FileStore {
+    /// A store at `path` in the format matching its extension.
+    pub fn new(path: impl Into<PathBuf>) -> FileStore {
+        let path = path.into();
+        let format = StoreFormat::for_path(&path);
+        FileStore { path, format, retention: Retention::default() }
+    }
+}
+
+impl TaskStore for FileStore {
+    fn load(&self) -> Result<Tasks, TaskError> {
+        persistence::load_in(&self.path, self.format)
+    }
+
+    fn save(&self, tasks: &Tasks) -> Result<(), TaskError> {
+        persistence::save_in(tasks, &self.path, self.format, &self.retention)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn plan_migration(&self) -> Result<Migration, TaskError> {
+        persistence::plan_migration_in(&self.path, self.format)
+    }
+
+    fn restore(&self, name: &str) -> Option<Result<Backup, TaskError>> {
+        Some(persistence::restore_backup(&self.path, self.format, name, &self.retention))
+    }
+
+    fn encrypt(&self, tasks: &Tasks, passphrase: Passphrase) -> Option<Result<usize, TaskError>> {
+        let encrypted = EncryptedStore { path: self.path.clone(), format: self.format, retention: self.retention, passphrase };
+        Some(encrypted.encrypt_plain_file(tasks))
+    }
+}
+
+/// Opens the store at `path`, or the default tasks file. The format comes from
+/// `format` when set, otherwise from the file extension. `retention` applies
+/// to the text formats, which keep backups. Text files written by `tasks
+/// encrypt` open as an `EncryptedStore` that asks for the passphrase.
+pub fn open(path: Option<&Path>, format: Option<StoreFormat>, retention: Retention) -> Box<dyn TaskStore> {
+    let default = paths::default_data_file();
+    let path = path.unwrap_or(&default);
+    match format.unwrap_or_else(|| StoreFormat::for_path(path)) {
+        StoreFormat::Sqlite => Box::new(SqliteStore::new(path)),
+        StoreFormat::Events => Box::new(EventStore::new(path)),
+        format if encrypted::is_encrypted(path) => {
+            Box::new(EncryptedStore { path: path.to_path_buf(), format, retention, passphrase: Passphrase::Ask })
+        }
+        format => Box::new(FileStore { path: path.to_path_buf(), format, retention }),
+    }
+}
+
+/// Where `tasks convert --to <format>` writes: `path` with the format's
+/// extension.
+pub fn converted_path(path: &Path, format: StoreFormat) -> PathBuf {
+    path.with_extension(format.extension())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn opens_by_extension_or_config() {
+        let store = FileStore::new("data/tasks.toml");
+        assert_eq!(store.format, StoreFormat::Toml);
+
+        let store = open(Some(Path::new("data/tasks.txt")), Some(StoreFormat::Json), Retention::default());
+        assert_eq!(store.path(), Path::new("data/tasks.txt"));
+
+        assert_eq!(open(None, None, Retention::default()).path(), paths::default_data_file());
+        assert!(open(Some(Path::new("data/tasks.db")), None, Retention::default()).query(&TaskQuery::default()).is_some());
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        let mut tasks = Tasks::new();
+        tasks.add_task("Task 1".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+
+        let yaml = FileStore::new(env::temp_dir().join("store_converts_between_formats.yaml"));
+        yaml.save(&tasks).unwrap();
+
+        let json = FileStore::new(converted_path(&yaml.path, StoreFormat::Json));
+        json.save(&yaml.load().unwrap()).unwrap();
+
+        assert!(json.path.to_string_lossy().ends_with("store_converts_between_formats.json"));
+        assert!(fs::read_to_string(&json.path).unwrap().trim_start().starts_with('{'));
+        assert_eq!(json.load().unwrap().tasks, tasks.tasks);
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::Write;
+
+use super::{chart::DayPoint, report::escape, Stats, TaskError};
+
+const WIDTH: f64 = 720.0;
+const PANEL_HEIGHT: f64 = 240.0;
+const MARGIN: f64 = 48.0;
+const HEADER_HEIGHT: f64 = 56.0;
+const BAR_ROW: f64 = 22.0;
+
+const DONE_COLOR: &str = "#2e7d32";
+const OPEN_COLOR: &str = "#1565c0";
+const AXIS_COLOR: &str = "#999";
+
+/// Writes a standalone SVG with a completion trend, open against done over
+/// time and a per-project breakdown. Each bar and point carries a `<title>`
+/// so viewers show the exact numbers on hover.
+pub fn write_dashboard(
+    writer: &mut impl Write,
+    stats: &Stats,
+    points: &[DayPoint],
+    projects: &[(String, Stats)],
+) -> Result<(), TaskError> {
+    let projects_height = MARGIN * 2.0 + BAR_ROW * projects.len().max(1) as f64;
+    let height = HEADER_HEIGHT + PANEL_HEIGHT * 2.0 + projects_height;
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = WIDTH,
+        h = height
+    )?;
+    writeln!(writer, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    writeln!(
+        writer,
+        r#"<text x="{}" y="32" font-size="18" font-weight="bold">{} tasks, {} completed ({}%), {} overdue</text>"#,
+        MARGIN, stats.total, stats.completed, stats.percent_completed, stats.overdue
+    )?;
+
+    write_completions(writer, points, HEADER_HEIGHT)?;
+    write_open_done(writer, points, HEADER_HEIGHT + PANEL_HEIGHT)?;
+    write_projects(writer, projects, HEADER_HEIGHT + PANEL_HEIGHT * 2.0)?;
+
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
+
+fn write_completions(writer: &mut impl Write, points: &[DayPoint], top: f64) -> Result<(), TaskError> {
+    let max = points.iter().map(|p| p.completed).max().unwrap_or(0).max(1);
+    let (left, bottom, plot_width, plot_height) = panel(writer, "Completions per day", top, max)?;
+
+    let slot = plot_width / points.len().max(1) as f64;
+    for (i, point) in points.iter().enumerate() {
+        if point.completed == 0 {
+            continue;
+        }
+        let height = plot_height * point.completed as f64 / max as f64;
+        writeln!(
+            writer,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}: {} completed</title></rect>"#,
+            left + slot * i as f64 + slot * 0.1,
+            bottom - height,
+            slot * 0.8,
+            height,
+            DONE_COLOR,
+            point.date,
+            point.completed
+        )?;
+    }
+
+    write_date_labels(writer, points, left, bottom, plot_width)
+}
+
+fn write_open_done(writer: &mut impl Write, points: &[DayPoint], top: f64) -> Result<(), TaskError> {
+    let max = points.iter().map(|p| p.open.max(p.done)).max().unwrap_or(0).max(1);
+    let (left, bottom, plot_width, plot_height) = panel(writer, "Open and done over time", top, max)?;
+
+    let step = if points.len() > 1 { plot_width / (points.len() - 1) as f64 } else { 0.0 };
+    let position = |i: usize, value: usize| (left + step * i as f64, bottom - plot_height * value as f64 / max as f64);
+
+    let open: Vec<usize> = points.iter().map(|p| p.open).collect();
+    let done: Vec<usize> = points.iter().map(|p| p.done).collect();
+    for (name, color, values) in [("open", OPEN_COLOR, open), ("done", DONE_COLOR, done)] {
+        let coordinates: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let (x, y) = position(i, *value);
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        writeln!(writer, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#, coordinates.join(" "), color)?;
+
+        for (i, (point, value)) in points.iter().zip(&values).enumerate() {
+            let (x, y) = position(i, *value);
+            writeln!(
+                writer,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="2.5" fill="{}"><title>{}: {} {}</title></circle>"#,
+                x, y, color, point.date, value, name
+            )?;
+        }
+    }
+
+    // Legend in the top right corner of the panel
+    for (i, (name, color)) in [("Open", OPEN_COLOR), ("Done", DONE_COLOR)].iter().enumerate() {
+        let x = WIDTH - MARGIN - 110.0 + 60.0 * i as f64;
+        writeln!(writer, r#"<rect x="{}" y="{}" width="10" height="10" fill="{}"/>"#, x, top + 14.0, color)?;
+        writeln!(writer, r#"<text x="{}" y="{}">{}</text>"#, x + 14.0, top + 23.0, name)?;
+    }
+
+    write_date_labels(writer, points, left, bottom, plot_width)
+}
+
+fn write_projects(writer: &mut impl Write, projects: &[(String, Stats)], top: f64) -> Result<(), TaskError> {
+    writeln!(writer, r#"<text x="{}" y="{}" font-size="14" font-weight="bold">Tasks per project</text>"#, MARGIN, top + 24.0)?;
+    if projects.is_empty() {
+        writeln!(writer, r#"<text x="{}" y="{}" fill="{}">No tasks</text>"#, MARGIN, top + MARGIN + 14.0, AXIS_COLOR)?;
+        return Ok(());
+    }
+
+    let label_width = 140.0;
+    let left = MARGIN + label_width;
+    let plot_width = WIDTH - left - MARGIN - 40.0;
+    let max = projects.iter().map(|(_, s)| s.total).max().unwrap_or(0).max(1);
+
+    for (i, (name, stats)) in projects.iter().enumerate() {
+        let y = top + MARGIN + BAR_ROW * i as f64;
+        let done = plot_width * stats.completed as f64 / max as f64;
+        let open = plot_width * (stats.total - stats.completed) as f64 / max as f64;
+        let name = escape(name);
+
+        writeln!(writer, r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#, left - 8.0, y + 13.0, name)?;
+        writeln!(
+            writer,
+            r#"<rect x="{}" y="{:.1}" width="{:.1}" height="16" fill="{}"><title>{}: {} done</title></rect>"#,
+            left, y, done, DONE_COLOR, name, stats.completed
+        )?;
+        writeln!(
+            writer,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="16" fill="{}"><title>{}: {} open</title></rect>"#,
+            left + done, y, open, OPEN_COLOR, name, stats.total - stats.completed
+        )?;
+        writeln!(writer, r#"<text x="{:.1}" y="{:.1}">{}</text>"#, left + done + open + 6.0, y + 13.0, stats.total)?;
+    }
+
+    Ok(())
+}
+
+// Draws the panel title and axes, returning the plot's left edge, baseline,
+// width and height
+fn panel(writer: &mut impl Write, title: &str, top: f64, max: usize) -> Result<(f64, f64, f64, f64), TaskError> {
+    let left = MARGIN;
+    let bottom = top + PANEL_HEIGHT - MARGIN;
+    let plot_width = WIDTH - MARGIN * 2.0;
+    let plot_height = PANEL_HEIGHT - MARGIN * 2.0;
+
+    writeln!(writer, r#"<text x="{}" y="{}" font-size="14" font-weight="bold">{}</text>"#, left, top + 24.0, title)?;
+    writeln!(
+        writer,
+        r#"<path d="M{l} {t} V{b} H{r}" fill="none" stroke="{c}"/>"#,
+        l = left,
+        t = bottom - plot_height,
+        b = bottom,
+        r = left + plot_width,
+        c = AXIS_COLOR
+    )?;
+    writeln!(writer, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, left - 6.0, bottom - plot_height + 4.0, max)?;
+    writeln!(writer, r#"<text x="{}" y="{}" text-anchor="end">0</text>"#, left - 6.0, bottom + 4.0)?;
+
+    Ok((left, bottom, plot_width, plot_height))
+}
+
+fn write_date_labels(writer: &mut impl Write, points: &[DayPoint], left: f64, bottom: f64, plot_width: f64) -> Result<(), TaskError> {
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        writeln!(writer, r#"<text x="{}" y="{}">{}</text>"#, left, bottom + 18.0, first.date)?;
+        writeln!(writer, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, left + plot_width, bottom + 18.0, last.date)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::tasks::{chart, stats::{self, StatsGroup}, Task};
+    use crate::tests_common::{task, TaskFixture};
+
+    fn render(tasks: &[Task]) -> String {
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        let points = chart::history(tasks, "2024-05-01".parse().unwrap(), "2024-05-10".parse().unwrap(), &Utc);
+        let projects = stats::grouped(tasks, StatsGroup::Project, now, 4);
+
+        let mut writer = Vec::new();
+        write_dashboard(&mut writer, &Stats::at(tasks, now, 4), &points, &projects).unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    fn sample() -> Vec<Task> {
+        let (due, created) = ("2024-05-20T09:00:00Z", "2024-05-01T09:00:00Z");
+        vec![
+            task(1, "Task 1", due).created(created).done_at("2024-05-03T09:00:00Z").in_project("R&D"),
+            task(2, "Task 2", due).created(created),
+            task(3, "Task 3", due).created(created),
+        ]
+    }
+
+    #[test]
+    fn writes_a_standalone_svg_with_three_charts() {
+        let output = render(&sample());
+
+        assert!(output.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(output.trim_end().ends_with("</svg>"));
+        // Nothing is loaded from elsewhere
+        assert!(!output.contains("href"));
+        assert!(output.contains(">3 tasks, 1 completed (33%), 0 overdue</text>"));
+        assert!(output.contains(">Completions per day</text>"));
+        assert!(output.contains(">Open and done over time</text>"));
+        assert!(output.contains(">Tasks per project</text>"));
+    }
+
+    #[test]
+    fn includes_hover_titles_and_escapes_names() {
+        let output = render(&sample());
+
+        assert!(output.contains("<title>2024-05-03: 1 completed</title>"));
+        assert!(output.contains("<title>2024-05-10: 2 open</title>"));
+        assert_eq!(output.matches("<polyline").count(), 2);
+        assert!(output.contains(">R&amp;D</text>"));
+        assert!(output.contains("<title>No project: 2 open</title>"));
+    }
+}
@@ -0,0 +1,356 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use chrono::Utc;
+use colored::Colorize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::{Task, TaskError};
+
+const SEPARATOR: &str = "  ";
+const ELLIPSIS: char = '…';
+// Columns are never squeezed below this when fitting to the terminal
+const MIN_COLUMN_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Id,
+    Name,
+    Description,
+    Due,
+    Status,
+    Project,
+    Tags,
+}
+
+impl Column {
+    pub fn defaults() -> Vec<Column> {
+        vec![Column::Id, Column::Name, Column::Description, Column::Due, Column::Status]
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Name => "Name",
+            Column::Description => "Description",
+            Column::Due => "Due",
+            Column::Status => "Status",
+            Column::Project => "Project",
+            Column::Tags => "Tags",
+        }
+    }
+
+    fn value(&self, task: &Task) -> String {
+        match self {
+            Column::Id => task.id.to_string(),
+            Column::Name => task.name.clone(),
+            Column::Description => task.description.clone(),
+            Column::Due => task.due_date.format("%Y-%m-%d %H:%M").to_string(),
+            Column::Status => if task.completed { "done" } else { "open" }.to_string(),
+            Column::Project => task.project.clone().unwrap_or_default(),
+            Column::Tags => task.tags.join(" "),
+        }
+    }
+
+    // Free text columns give up width first when the table is too wide
+    fn is_flexible(&self) -> bool {
+        matches!(self, Column::Name | Column::Description | Column::Tags)
+    }
+
+    fn right_aligned(&self) -> bool {
+        matches!(self, Column::Id)
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "id" => Ok(Column::Id),
+            "name" => Ok(Column::Name),
+            "description" | "desc" => Ok(Column::Description),
+            "due" | "due_date" => Ok(Column::Due),
+            "status" | "completed" => Ok(Column::Status),
+            "project" => Ok(Column::Project),
+            "tags" | "tag" => Ok(Column::Tags),
+            other => Err(format!("Unknown column: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Id => write!(f, "id"),
+            Column::Name => write!(f, "name"),
+            Column::Description => write!(f, "description"),
+            Column::Due => write!(f, "due"),
+            Column::Status => write!(f, "status"),
+            Column::Project => write!(f, "project"),
+            Column::Tags => write!(f, "tags"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RowStyle {
+    Plain,
+    Overdue,
+    DueToday,
+    Completed,
+}
+
+impl RowStyle {
+    fn for_task(task: &Task) -> RowStyle {
+        let now = Utc::now();
+        if task.completed {
+            RowStyle::Completed
+        } else if task.due_date < now {
+            RowStyle::Overdue
+        } else if task.due_date.date_naive() == now.date_naive() {
+            RowStyle::DueToday
+        } else {
+            RowStyle::Plain
+        }
+    }
+
+    fn paint(&self, line: &str) -> String {
+        match self {
+            RowStyle::Plain => line.to_string(),
+            RowStyle::Overdue => line.red().to_string(),
+            RowStyle::DueToday => line.yellow().to_string(),
+            RowStyle::Completed => line.dimmed().to_string(),
+        }
+    }
+}
+
+/// Renders tasks as an aligned table.
+///
+/// Widths are measured in terminal cells so wide characters line up. When
+/// `max_width` is given, the name and description columns are truncated until
+/// the table fits.
+pub fn write_table(
+    writer: &mut impl Write,
+    tasks: &[Task],
+    columns: &[Column],
+    max_width: Option<usize>,
+    color: bool,
+) -> Result<(), TaskError> {
+    if tasks.is_empty() {
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = tasks
+        .iter()
+        .map(|task| columns.iter().map(|c| c.value(task)).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].width())
+                .chain(std::iter::once(column.header().width()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    if let Some(max_width) = max_width {
+        fit_widths(&mut widths, columns, max_width);
+    }
+
+    let header: Vec<String> = columns.iter().map(|c| c.header().to_string()).collect();
+    let header_line = format_row(&header, columns, &widths);
+    if color {
+        writeln!(writer, "{}", header_line.bold())?;
+    } else {
+        writeln!(writer, "{}", header_line)?;
+    }
+
+    let rule_width = widths.iter().sum::<usize>() + SEPARATOR.len() * widths.len().saturating_sub(1);
+    writeln!(writer, "{}", "─".repeat(rule_width))?;
+
+    for (task, row) in tasks.iter().zip(rows) {
+        let line = format_row(&row, columns, &widths);
+        if color {
+            writeln!(writer, "{}", RowStyle::for_task(task).paint(&line))?;
+        } else {
+            writeln!(writer, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn fit_widths(widths: &mut [usize], columns: &[Column], max_width: usize) {
+    let separators = SEPARATOR.len() * widths.len().saturating_sub(1);
+
+    while widths.iter().sum::<usize>() + separators > max_width {
+        let widest = widths
+            .iter()
+            .enumerate()
+            .filter(|(i, w)| columns[*i].is_flexible() && **w > MIN_COLUMN_WIDTH)
+            .max_by_key(|(_, w)| **w)
+            .map(|(i, _)| i);
+
+        match widest {
+            Some(i) => widths[i] -= 1,
+            None => break,
+        }
+    }
+}
+
+fn format_row(cells: &[String], columns: &[Column], widths: &[usize]) -> String {
+    let formatted: Vec<String> = cells
+        .iter()
+        .zip(columns)
+        .zip(widths)
+        .map(|((cell, column), width)| {
+            let cell = truncate(cell, *width);
+            let padding = " ".repeat(width - cell.width());
+            if column.right_aligned() {
+                format!("{}{}", padding, cell)
+            } else {
+                format!("{}{}", cell, padding)
+            }
+        })
+        .collect();
+
+    formatted.join(SEPARATOR).trim_end().to_string()
+}
+
+/// Cuts `text` down to `width` terminal cells, marking the cut with an ellipsis.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        result.push(c);
+        used += char_width;
+    }
+    if width > 0 {
+        result.push(ELLIPSIS);
+    }
+
+    result
+}
+
+/// Width of the attached terminal, if stdout is one.
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w as usize)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_common::create_tasks_completion;
+
+    use super::*;
+
+    fn render(tasks: &[Task], columns: &[Column], max_width: Option<usize>, color: bool) -> String {
+        let mut writer = Vec::new();
+        write_table(&mut writer, tasks, columns, max_width, color).unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn parse_column_from_str() {
+        assert!(matches!(Column::from_str("id"), Ok(Column::Id)));
+        assert!(matches!(Column::from_str("due"), Ok(Column::Due)));
+        assert!(matches!(Column::from_str("desc"), Ok(Column::Description)));
+        assert!(Column::from_str("priority").is_err());
+    }
+
+    #[test]
+    fn aligns_columns() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[1].name = "A much longer name".to_string();
+
+        let output = render(&tasks.tasks, &[Column::Id, Column::Name, Column::Status], None, false);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("ID  Name"));
+        let status_at = lines[0].find("Status").unwrap();
+        assert_eq!(lines[2].find("done"), Some(status_at));
+        assert_eq!(lines[3].find("done"), Some(status_at));
+        assert_eq!(lines[4].find("open"), Some(status_at));
+    }
+
+    #[test]
+    fn only_renders_selected_columns() {
+        let tasks = create_tasks_completion();
+
+        let output = render(&tasks.tasks, &[Column::Name], None, false);
+
+        assert!(output.contains("Task 1"));
+        assert!(!output.contains("Text for task1"));
+        assert!(!output.contains("Status"));
+    }
+
+    #[test]
+    fn measures_wide_characters() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[0].name = "日本語".to_string();
+
+        let output = render(&tasks.tasks, &[Column::Name, Column::Status], None, false);
+        let lines: Vec<&str> = output.lines().collect();
+
+        // "日本語" is six cells wide, the same as "Task 2"
+        assert_eq!(lines[2].width(), lines[3].width());
+    }
+
+    #[test]
+    fn truncates_to_max_width() {
+        let mut tasks = create_tasks_completion();
+        tasks.tasks[0].description = "x".repeat(200);
+
+        let output = render(&tasks.tasks, &Column::defaults(), Some(60), false);
+
+        for line in output.lines() {
+            assert!(line.width() <= 60, "line too wide: {}", line);
+        }
+        assert!(output.contains(ELLIPSIS));
+    }
+
+    #[test]
+    fn truncate_marks_cut() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a longer name", 6), "a lon…");
+        assert_eq!(truncate("日本語", 4), "日…");
+    }
+
+    #[test]
+    fn colors_rows_by_state() {
+        colored::control::set_override(true);
+        let tasks = create_tasks_completion();
+
+        let output = render(&tasks.tasks, &[Column::Name], None, true);
+
+        // completed rows are dimmed
+        assert!(output.contains("\u{1b}[2mTask 1"));
+    }
+
+    #[test]
+    fn no_escape_codes_without_color() {
+        let tasks = create_tasks_completion();
+
+        let output = render(&tasks.tasks, &Column::defaults(), None, false);
+
+        assert!(!output.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn empty_list_renders_nothing() {
+        assert_eq!(render(&[], &Column::defaults(), None, false), "");
+    }
+}
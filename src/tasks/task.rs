@@ -11,6 +11,16 @@ pub struct Task {
     #[serde(with = "serde_with::rust::display_fromstr")]
     pub due_date: DateTime<Utc>,
     pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// When the task was added, unknown for older files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the task was last marked complete, unknown for older files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
 } 
 
 impl fmt::Display for Task {
@@ -39,6 +49,10 @@ impl Task {
             description,
             due_date,
             completed: false,
+            project: None,
+            tags: Vec::new(),
+            created_at: Some(Utc::now()),
+            completed_at: None,
         } 
     } 
 }
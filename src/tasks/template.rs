@@ -0,0 +1,346 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{format::{Item, StrftimeItems}, Utc};
+use unicode_width::UnicodeWidthStr;
+
+use super::Task;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// A user supplied format for rendering a single task, e.g.
+/// `"{id:>4} {due:%b %d} {?completed:✓|·} {name}"`.
+///
+/// * `{field}` inserts a field: `id`, `name`, `description`, `due`, `status`,
+///   `project`, `tags`
+/// * `{field:>N}`, `{field:<N}` and `{field:^N}` pad to `N` terminal cells
+/// * `{due:%b %d}` formats the due date with a strftime pattern, which may
+///   follow a padding spec as in `{due:<8%b %d}`
+/// * `{?flag:yes}` and `{?flag:yes|no}` show text depending on `completed`
+///   or `overdue`
+/// * `{{` and `}}` insert literal braces
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field {
+        field: Field,
+        padding: Option<(Align, usize)>,
+        date_format: Option<String>,
+    },
+    Conditional {
+        flag: Flag,
+        then: String,
+        otherwise: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Id,
+    Name,
+    Description,
+    Due,
+    Status,
+    Project,
+    Tags,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flag {
+    Completed,
+    Overdue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    /// 1-based character position the problem was found at
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "template error at column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '{' if chars.get(i + 1) == Some(&'{') => {
+                    literal.push('{');
+                    i += 2;
+                }
+                '}' if chars.get(i + 1) == Some(&'}') => {
+                    literal.push('}');
+                    i += 2;
+                }
+                '}' => return Err(error(i, "unmatched '}', use '}}' for a literal brace")),
+                '{' => {
+                    let close = chars[i + 1..]
+                        .iter()
+                        .position(|c| *c == '}')
+                        .map(|offset| i + 1 + offset)
+                        .ok_or_else(|| error(i, "unclosed '{'"))?;
+                    let body: String = chars[i + 1..close].iter().collect();
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_placeholder(&body, i + 1)?);
+                    i = close + 1;
+                }
+                c => {
+                    literal.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Template { source: source.to_string(), segments })
+    }
+
+    pub fn render(&self, task: &Task) -> String {
+        let mut out = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Field { field, padding, date_format } => {
+                    let value = field_value(task, *field, date_format.as_deref());
+                    out.push_str(&pad(&value, *padding));
+                }
+                Segment::Conditional { flag, then, otherwise } => {
+                    if flag_value(task, *flag) {
+                        out.push_str(then);
+                    } else {
+                        out.push_str(otherwise);
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Template::parse(s)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn error(index: usize, message: &str) -> TemplateError {
+    TemplateError { column: index + 1, message: message.to_string() }
+}
+
+// `start` is the index of the first character after the opening brace
+fn parse_placeholder(body: &str, start: usize) -> Result<Segment, TemplateError> {
+    if let Some(rest) = body.strip_prefix('?') {
+        let (name, text) = rest
+            .split_once(':')
+            .ok_or_else(|| error(start, "conditional needs text, e.g. {?completed:✓}"))?;
+        let flag = match name {
+            "completed" | "done" => Flag::Completed,
+            "overdue" => Flag::Overdue,
+            other => return Err(error(start + 1, &format!("unknown condition '{}', expected completed or overdue", other))),
+        };
+        let (then, otherwise) = text.split_once('|').unwrap_or((text, ""));
+
+        return Ok(Segment::Conditional { flag, then: then.to_string(), otherwise: otherwise.to_string() });
+    }
+
+    let (name, spec) = match body.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (body, None),
+    };
+
+    let field = match name {
+        "id" => Field::Id,
+        "name" => Field::Name,
+        "description" | "desc" => Field::Description,
+        "due" | "due_date" => Field::Due,
+        "status" => Field::Status,
+        "project" => Field::Project,
+        "tags" => Field::Tags,
+        "" => return Err(error(start, "empty placeholder")),
+        other => return Err(error(start, &format!("unknown field '{}'", other))),
+    };
+
+    let mut padding = None;
+    let mut date_format = None;
+
+    if let Some(spec) = spec {
+        let spec_start = start + name.chars().count() + 1;
+        let mut rest = spec;
+
+        let align = match rest.chars().next() {
+            Some('<') => Some(Align::Left),
+            Some('>') => Some(Align::Right),
+            Some('^') => Some(Align::Center),
+            _ => None,
+        };
+        if let Some(align) = align {
+            rest = &rest[1..];
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            if digits.is_empty() {
+                return Err(error(spec_start + 1, "expected a width after the alignment"));
+            }
+            rest = &rest[digits.len()..];
+            let width = digits.parse().map_err(|_| error(spec_start + 1, "width is too large"))?;
+            padding = Some((align, width));
+        }
+
+        if !rest.is_empty() {
+            if field != Field::Due {
+                return Err(error(spec_start, &format!("'{}' is not a valid spec for {}", rest, name)));
+            }
+            if !rest.contains('%') || StrftimeItems::new(rest).any(|item| matches!(item, Item::Error)) {
+                return Err(error(spec_start, &format!("invalid date format '{}'", rest)));
+            }
+            date_format = Some(rest.to_string());
+        }
+    }
+
+    Ok(Segment::Field { field, padding, date_format })
+}
+
+fn field_value(task: &Task, field: Field, date_format: Option<&str>) -> String {
+    match field {
+        Field::Id => task.id.to_string(),
+        Field::Name => task.name.clone(),
+        Field::Description => task.description.clone(),
+        Field::Due => task.due_date.format(date_format.unwrap_or(DEFAULT_DATE_FORMAT)).to_string(),
+        Field::Status => if task.completed { "done" } else { "open" }.to_string(),
+        Field::Project => task.project.clone().unwrap_or_default(),
+        Field::Tags => task.tags.join(" "),
+    }
+}
+
+fn flag_value(task: &Task, flag: Flag) -> bool {
+    match flag {
+        Flag::Completed => task.completed,
+        Flag::Overdue => !task.completed && task.due_date < Utc::now(),
+    }
+}
+
+fn pad(value: &str, padding: Option<(Align, usize)>) -> String {
+    let Some((align, width)) = padding else {
+        return value.to_string();
+    };
+
+    let fill = width.saturating_sub(value.width());
+    match align {
+        Align::Left => format!("{}{}", value, " ".repeat(fill)),
+        Align::Right => format!("{}{}", " ".repeat(fill), value),
+        Align::Center => {
+            let left = fill / 2;
+            format!("{}{}{}", " ".repeat(left), value, " ".repeat(fill - left))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task() -> Task {
+        Task::new(7, "Write report".to_string(), "Quarterly".to_string(), "2024-05-02T09:30:00Z".to_string())
+    }
+
+    fn render(source: &str, task: &Task) -> String {
+        Template::parse(source).unwrap().render(task)
+    }
+
+    #[test]
+    fn renders_fields_and_literals() {
+        assert_eq!(render("#{id}: {name} ({description})", &task()), "#7: Write report (Quarterly)");
+    }
+
+    #[test]
+    fn pads_fields() {
+        assert_eq!(render("[{id:>4}]", &task()), "[   7]");
+        assert_eq!(render("[{id:<3}]", &task()), "[7  ]");
+        assert_eq!(render("[{id:^5}]", &task()), "[  7  ]");
+    }
+
+    #[test]
+    fn formats_due_dates() {
+        assert_eq!(render("{due:%b %d}", &task()), "May 02");
+        assert_eq!(render("{due}", &task()), "2024-05-02 09:30");
+        assert_eq!(render("[{due:>8%b %d}]", &task()), "[  May 02]");
+    }
+
+    #[test]
+    fn renders_conditionals() {
+        let mut done = task();
+        done.completed = true;
+
+        assert_eq!(render("{?completed:✓|·} {name}", &task()), "· Write report");
+        assert_eq!(render("{?completed:✓|·} {name}", &done), "✓ Write report");
+        assert_eq!(render("{?overdue:!}", &task()), "!");
+        assert_eq!(render("{?overdue:!}", &done), "");
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(render("{{{id}}}", &task()), "{7}");
+    }
+
+    #[test]
+    fn reports_unknown_field_with_column() {
+        let err = Template::parse("{id} {priority}").unwrap_err();
+        assert_eq!(err.column, 7);
+        assert_eq!(err.to_string(), "template error at column 7: unknown field 'priority'");
+    }
+
+    #[test]
+    fn reports_unclosed_brace() {
+        let err = Template::parse("{id} {name").unwrap_err();
+        assert_eq!(err.column, 6);
+        assert!(err.message.contains("unclosed"));
+    }
+
+    #[test]
+    fn reports_invalid_specs() {
+        assert!(Template::parse("{name:%Y}").is_err());
+        assert!(Template::parse("{due:%Q}").is_err());
+        assert!(Template::parse("{id:>}").is_err());
+        assert!(Template::parse("{?priority:x}").is_err());
+        assert!(Template::parse("{?completed}").is_err());
+        assert!(Template::parse("}").is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use chrono::DateTime;
+use ratatui::{
+    backend::Backend,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal,
+};
+
+use super::{store::TaskStore, update, Task, TaskError, Tasks};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatusFilter {
+    All,
+    Open,
+    Done,
+}
+
+impl StatusFilter {
+    fn next(self) -> StatusFilter {
+        match self {
+            StatusFilter::All => StatusFilter::Open,
+            StatusFilter::Open => StatusFilter::Done,
+            StatusFilter::Done => StatusFilter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            StatusFilter::All => "all",
+            StatusFilter::Open => "open",
+            StatusFilter::Done => "done",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Normal,
+    /// Typing "name; description; due date" for a new task
+    Add(String),
+    /// Editing the selected task as update fields, e.g. "name:New, project:work"
+    Edit(String),
+    /// Typing text that task names must contain
+    Search(String),
+    ConfirmDelete,
+}
+
+/// What the caller should do after a key press.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    None,
+    /// Tasks changed and should be saved
+    Save,
+    Quit,
+}
+
+/// State of the full-screen task browser. Drawing and key handling work on a
+/// borrowed `Tasks`, so screens can be tested against a buffer backend.
+#[derive(Debug)]
+pub struct App {
+    selected: usize,
+    mode: Mode,
+    filter: StatusFilter,
+    search: String,
+    message: Option<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            mode: Mode::Normal,
+            filter: StatusFilter::All,
+            search: String::new(),
+            message: None,
+        }
+    }
+
+    fn visible<'a>(&self, tasks: &'a Tasks) -> Vec<&'a Task> {
+        let search = self.search.to_lowercase();
+        tasks
+            .tasks
+            .iter()
+            .filter(|t| match self.filter {
+                StatusFilter::All => true,
+                StatusFilter::Open => !t.completed,
+                StatusFilter::Done => t.completed,
+            })
+            .filter(|t| search.is_empty() || t.name.to_lowercase().contains(&search))
+            .collect()
+    }
+
+    fn selected_id(&self, tasks: &Tasks) -> Option<u32> {
+        self.visible(tasks).get(self.selected).map(|t| t.id)
+    }
+
+    fn clamp_selection(&mut self, tasks: &Tasks) {
+        let len = self.visible(tasks).len();
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, tasks: &mut Tasks) -> Action {
+        let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+        match mode {
+            Mode::Normal => self.handle_normal(key, tasks),
+            Mode::ConfirmDelete => {
+                if let (KeyCode::Char('y'), Some(id)) = (key.code, self.selected_id(tasks)) {
+                    tasks.delete_task(id);
+                    self.clamp_selection(tasks);
+                    self.message = Some(format!("Deleted task {}", id));
+                    Action::Save
+                } else {
+                    self.message = None;
+                    Action::None
+                }
+            }
+            Mode::Search(_) if key.code == KeyCode::Esc => {
+                self.search.clear();
+                Action::None
+            }
+            Mode::Add(_) | Mode::Edit(_) if key.code == KeyCode::Esc => {
+                self.message = None;
+                Action::None
+            }
+            Mode::Add(input) => self.handle_input(key, input, Mode::Add, Self::submit_add, tasks),
+            Mode::Edit(input) => self.handle_input(key, input, Mode::Edit, Self::submit_edit, tasks),
+            Mode::Search(input) => {
+                let action = self.handle_input(key, input, Mode::Search, |app, input, _| {
+                    app.search = input;
+                    Action::None
+                }, tasks);
+                // Narrow the list while typing
+                if let Mode::Search(current) = &self.mode {
+                    self.search = current.clone();
+                }
+                self.clamp_selection(tasks);
+                action
+            }
+        }
+    }
+
+    fn handle_normal(&mut self, key: KeyEvent, tasks: &mut Tasks) -> Action {
+        self.message = None;
+        let len = self.visible(tasks).len();
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < len => self.selected += 1,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('a') => self.mode = Mode::Add(String::new()),
+            KeyCode::Char('e') => {
+                if let Some(id) = self.selected_id(tasks) {
+                    let task = tasks.tasks.iter().find(|t| t.id == id).expect("selected task exists");
+                    self.mode = Mode::Edit(format!("name:{}, description:{}", update::escape_value(&task.name), update::escape_value(&task.description)));
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(id) = self.selected_id(tasks) {
+                    if tasks.complete_task(id).is_ok() {
+                        self.clamp_selection(tasks);
+                        self.message = Some(format!("Completed task {}", id));
+                        return Action::Save;
+                    }
+                }
+            }
+            KeyCode::Char('d') if self.selected_id(tasks).is_some() => self.mode = Mode::ConfirmDelete,
+            KeyCode::Char('f') => {
+                self.filter = self.filter.next();
+                self.clamp_selection(tasks);
+            }
+            KeyCode::Char('/') => self.mode = Mode::Search(self.search.clone()),
+            _ => {}
+        }
+
+        Action::None
+    }
+
+    fn handle_input(
+        &mut self,
+        key: KeyEvent,
+        mut input: String,
+        mode: fn(String) -> Mode,
+        submit: impl FnOnce(&mut App, String, &mut Tasks) -> Action,
+        tasks: &mut Tasks,
+    ) -> Action {
+        match key.code {
+            KeyCode::Enter => return submit(self, input, tasks),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+        self.mode = mode(input);
+        Action::None
+    }
+
+    fn submit_add(&mut self, input: String, tasks: &mut Tasks) -> Action {
+        let parts: Vec<&str> = input.splitn(3, ';').map(str::trim).collect();
+        let (name, description, due_date) = match parts.as_slice() {
+            [name, description, due_date] if !name.is_empty() => (*name, *description, *due_date),
+            _ => {
+                self.message = Some("Expected: name; description; due date".to_string());
+                self.mode = Mode::Add(input);
+                return Action::None;
+            }
+        };
+
+        // Task::new panics on a bad date, so check it first
+        if DateTime::parse_from_str(due_date, "%+").is_err() {
+            self.message = Some(format!("Invalid due date '{}', use e.g. 2024-05-01T12:00:00Z", due_date));
+            self.mode = Mode::Add(input);
+            return Action::None;
+        }
+
+        tasks.add_task(name.to_string(), description.to_string(), due_date.to_string());
+        self.message = Some(format!("Added '{}'", name));
+        Action::Save
+    }
+
+    fn submit_edit(&mut self, input: String, tasks: &mut Tasks) -> Action {
+        let Some(id) = self.selected_id(tasks) else {
+            return Action::None;
+        };
+
+        match update::parse_update_fields(&input).and_then(|fields| tasks.update_task(id, fields)) {
+            Ok(()) => {
+                self.message = Some(format!("Updated task {}", id));
+                self.clamp_selection(tasks);
+                Action::Save
+            }
+            Err(e) => {
+                self.message = Some(e.to_string());
+                self.mode = Mode::Edit(input);
+                Action::None
+            }
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, tasks: &Tasks) {
+        let [main, status] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(main);
+
+        let visible = self.visible(tasks);
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|task| {
+                let check = if task.completed { "✓" } else { " " };
+                let item = ListItem::new(format!("{} {:>3} {}", check, task.id, task.name));
+                if task.completed {
+                    item.style(Style::new().add_modifier(Modifier::DIM))
+                } else {
+                    item
+                }
+            })
+            .collect();
+
+        let mut title = format!(" Tasks ({}) [{}] ", visible.len(), self.filter.label());
+        if !self.search.is_empty() {
+            title.push_str(&format!("/{} ", self.search));
+        }
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        let mut state = ListState::default();
+        if !visible.is_empty() {
+            state.select(Some(self.selected));
+        }
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let detail = match visible.get(self.selected) {
+            Some(task) => detail_lines(task),
+            None => vec![Line::from("No tasks")],
+        };
+        frame.render_widget(
+            Paragraph::new(detail).block(Block::bordered().title(" Details ")).wrap(Wrap { trim: false }),
+            detail_area,
+        );
+
+        let status_line = match &self.mode {
+            Mode::Add(input) => Line::from(format!("Add (name; description; due): {}_", input)),
+            Mode::Edit(input) => Line::from(format!("Edit: {}_", input)),
+            Mode::Search(input) => Line::from(format!("Search: {}_", input)),
+            Mode::ConfirmDelete => Line::from("Delete selected task? (y/n)".red()),
+            Mode::Normal => match &self.message {
+                Some(message) => Line::from(message.as_str()),
+                None => Line::from("a add  e edit  c complete  d delete  f filter  / search  q quit".dim()),
+            },
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+}
+
+fn detail_lines(task: &Task) -> Vec<Line<'_>> {
+    let field = |label: &'static str, value: String| Line::from(vec![Span::from(label).bold(), Span::from(value)]);
+
+    vec![
+        field("ID:          ", task.id.to_string()),
+        field("Name:        ", task.name.clone()),
+        field("Description: ", task.description.clone()),
+        field("Due:         ", task.due_date.format("%Y-%m-%d %H:%M %Z").to_string()),
+        field("Status:      ", if task.completed { "done" } else { "open" }.to_string()),
+        field("Project:     ", task.project.clone().unwrap_or_default()),
+        field("Tags:        ", task.tags.join(" ")),
+    ]
+}
+
+/// Runs the interactive UI until the user quits, saving after every change.
+pub fn run(tasks: &mut Tasks, store: &dyn TaskStore) -> Result<(), TaskError> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, tasks, store);
+    ratatui::restore();
+    result
+}
+
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, tasks: &mut Tasks, store: &dyn TaskStore) -> Result<(), TaskError> {
+    let mut app = App::new();
+
+    loop {
+        terminal.draw(|frame| app.draw(frame, tasks))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match app.handle_key(key, tasks) {
+            Action::Quit => return Ok(()),
+            Action::Save => {
+                if let Err(e) = store.save(tasks) {
+                    app.message = Some(format!("Failed to save tasks: {}", e));
+                }
+            }
+            Action::None => {}
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, crossterm::event::KeyModifiers};
+
+    use crate::tests_common::create_tasks_completion;
+
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(app: &mut App, tasks: &mut Tasks, text: &str) {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c)), tasks);
+        }
+    }
+
+    fn screen(app: &App, tasks: &Tasks) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|frame| app.draw(frame, tasks)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                text.push_str(buffer[(x, y)].symbol());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn renders_list_and_details() {
+        let tasks = create_tasks_completion();
+        let app = App::new();
+
+        let output = screen(&app, &tasks);
+
+        assert!(output.contains("Tasks (3) [all]"));
+        assert!(output.contains(">"));
+        assert!(output.contains("Task 3"));
+        assert!(output.contains("Name:        Task 1"));
+        assert!(output.contains("q quit"));
+    }
+
+    #[test]
+    fn moves_selection_and_updates_details() {
+        let mut tasks = create_tasks_completion();
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Down), &mut tasks);
+        app.handle_key(key(KeyCode::Char('j')), &mut tasks);
+        app.handle_key(key(KeyCode::Char('j')), &mut tasks);
+
+        assert_eq!(app.selected, 2);
+        assert!(screen(&app, &tasks).contains("Name:        Task 3"));
+    }
+
+    #[test]
+    fn completes_selected_task() {
+        let mut tasks = create_tasks_completion();
+        let mut app = App::new();
+        app.handle_key(key(KeyCode::Down), &mut tasks);
+        app.handle_key(key(KeyCode::Down), &mut tasks);
+
+        let action = app.handle_key(key(KeyCode::Char('c')), &mut tasks);
+
+        assert_eq!(action, Action::Save);
+        assert!(tasks.tasks[2].completed);
+        assert!(screen(&app, &tasks).contains("Completed task 3"));
+    }
+
+    #[test]
+    fn adds_task_from_prompt() {
+        let mut tasks = Tasks::new();
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Char('a')), &mut tasks);
+        type_text(&mut app, &mut tasks, "New; Desc; 2024-05-01T12:00:00Z");
+        assert!(screen(&app, &tasks).contains("Add (name; description; due): New; Desc"));
+
+        let action = app.handle_key(key(KeyCode::Enter), &mut tasks);
+
+        assert_eq!(action, Action::Save);
+        assert_eq!(tasks.tasks.len(), 1);
+        assert_eq!(tasks.tasks[0].name, "New");
+        assert_eq!(tasks.tasks[0].description, "Desc");
+    }
+
+    #[test]
+    fn rejects_invalid_due_date_on_add() {
+        let mut tasks = Tasks::new();
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Char('a')), &mut tasks);
+        type_text(&mut app, &mut tasks, "New; Desc; tomorrow");
+        let action = app.handle_key(key(KeyCode::Enter), &mut tasks);
+
+        assert_eq!(action, Action::None);
+        assert!(tasks.tasks.is_empty());
+        assert!(matches!(app.mode, Mode::Add(_)));
+    }
+
+    #[test]
+    fn edits_selected_task() {
+        let mut tasks = create_tasks_completion();
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Char('e')), &mut tasks);
+        assert_eq!(app.mode, Mode::Edit("name:Task 1, description:Text for task1".to_string()));
+        for _ in 0.."description:Text for task1".len() {
+            app.handle_key(key(KeyCode::Backspace), &mut tasks);
+        }
+        type_text(&mut app, &mut tasks, "project:work");
+        let action = app.handle_key(key(KeyCode::Enter), &mut tasks);
+
+        assert_eq!(action, Action::Save);
+        assert_eq!(tasks.tasks[0].project, Some("work".to_string()));
+    }
+
+    #[test]
+    fn edits_values_with_commas() {
+        let mut tasks = Tasks::new();
+        tasks.add_task("Eggs, milk".to_string(), "Oat, not soy".to_string(), "2024-05-01T09:00:00Z".to_string());
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Char('e')), &mut tasks);
+        assert_eq!(app.mode, Mode::Edit("name:Eggs\\, milk, description:Oat\\, not soy".to_string()));
+        type_text(&mut app, &mut tasks, "\\, no sugar");
+        let action = app.handle_key(key(KeyCode::Enter), &mut tasks);
+
+        assert_eq!(action, Action::Save);
+        assert_eq!(tasks.tasks[0].name, "Eggs, milk");
+        assert_eq!(tasks.tasks[0].description, "Oat, not soy, no sugar");
+    }
+
+    #[test]
+    fn deletes_after_confirmation() {
+        let mut tasks = create_tasks_completion();
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Char('d')), &mut tasks);
+        assert!(screen(&app, &tasks).contains("Delete selected task? (y/n)"));
+        assert_eq!(app.handle_key(key(KeyCode::Char('n')), &mut tasks), Action::None);
+        assert_eq!(tasks.tasks.len(), 3);
+
+        app.handle_key(key(KeyCode::Char('d')), &mut tasks);
+        assert_eq!(app.handle_key(key(KeyCode::Char('y')), &mut tasks), Action::Save);
+        assert_eq!(tasks.tasks.len(), 2);
+    }
+
+    #[test]
+    fn filters_by_status_and_search() {
+        let mut tasks = create_tasks_completion();
+        let mut app = App::new();
+
+        app.handle_key(key(KeyCode::Char('f')), &mut tasks);
+        let output = screen(&app, &tasks);
+        assert!(output.contains("Tasks (1) [open]"));
+        assert!(output.contains("Name:        Task 3"));
+
+        app.handle_key(key(KeyCode::Char('f')), &mut tasks);
+        app.handle_key(key(KeyCode::Char('/')), &mut tasks);
+        type_text(&mut app, &mut tasks, "2");
+        app.handle_key(key(KeyCode::Enter), &mut tasks);
+        assert!(screen(&app, &tasks).contains("Tasks (1) [done] /2"));
+    }
+
+    #[test]
+    fn quits() {
+        let mut tasks = Tasks::new();
+        let mut app = App::new();
+
+        assert_eq!(app.handle_key(key(KeyCode::Char('q')), &mut tasks), Action::Quit);
+    }
+}
@@ -14,6 +14,9 @@ pub struct UpdateFields {
     pub description: Option<String>,
     pub due_date: Option<String>,
     pub completed: Option<String>,
+    /// `Some(None)` clears the project
+    pub project: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
 }
 
 impl UpdateFields {
@@ -23,6 +26,8 @@ impl UpdateFields {
             description: None,
             due_date: None,
             completed: None,
+            project: None,
+            tags: None,
         } 
     } 
 } 
@@ -38,6 +43,8 @@ impl FromStr for UpdateFields {
             description: fields.get(1).map(|s| s.to_string()),
             due_date: fields.get(2).map(|s| s.to_string()),
             completed: fields.get(3).map(|s| s.to_string()),
+            project: None,
+            tags: None,
         })
     }
 } 
@@ -45,7 +52,7 @@ impl FromStr for UpdateFields {
 pub fn parse_update_fields(update_args: &str) -> Result<UpdateFields, TaskError>{
     let mut update_fields = UpdateFields::default();
 
-    for pair in update_args.split(", ") {
+    for pair in split_fields(update_args) {
         let kv: Vec<_> = pair.splitn(2, ':').collect();
 
         if kv.len() != 2 {
@@ -70,6 +77,11 @@ pub fn parse_update_fields(update_args: &str) -> Result<UpdateFields, TaskError>
                 } 
                 update_fields.completed = Some(value.to_string());
             },
+            "project" => {
+                let project = value.trim();
+                update_fields.project = Some((!project.is_empty()).then(|| project.to_string()));
+            },
+            "tags" => update_fields.tags = Some(parse_tags(value)),
             _ => {
                 let err_string = format!("Unknown field key: {}", key);
                 return Err(TaskError::InvalidInput(err_string))
@@ -80,6 +92,44 @@ pub fn parse_update_fields(update_args: &str) -> Result<UpdateFields, TaskError>
     Ok(update_fields)
 } 
 
+/// Escapes commas in `value` so `parse_update_fields` keeps it in one field.
+pub fn escape_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,")
+}
+
+// Splits on ", " unless the comma is escaped as `\,`. `\\` is a backslash,
+// other backslashes are kept as typed.
+fn split_fields(update_args: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = update_args.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("fields is never empty");
+        match (c, chars.peek()) {
+            ('\\', Some(&next @ (',' | '\\'))) => {
+                field.push(next);
+                chars.next();
+            }
+            (',', Some(' ')) => {
+                chars.next();
+                fields.push(String::new());
+            }
+            _ => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Splits a space separated tag list, dropping empty and repeated tags.
+pub fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value.split_whitespace() {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        } 
+    } 
+    tags
+} 
+
 fn valid_due_date_format(value: &str) -> bool {
     let reg = Regex::new(r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\+\d{2}:\d{2}").unwrap();
     reg.is_match(value)
@@ -94,7 +144,7 @@ fn valid_completed_format(value: &str) -> bool {
 mod tests {
     use crate::tasks::TaskError;
 
-    use super::parse_update_fields;
+    use super::{escape_value, parse_update_fields, parse_tags};
 
     #[test]
     fn test_parse_update_invalid() {
@@ -114,6 +164,35 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_parse_update_project_and_tags() {
+        let result = parse_update_fields("project:work, tags:urgent home urgent").unwrap();
+
+        assert_eq!(result.project, Some(Some("work".to_string())));
+        assert_eq!(result.tags, Some(vec!["urgent".to_string(), "home".to_string()]));
+
+        let result = parse_update_fields("project:").unwrap();
+        assert_eq!(result.project, Some(None));
+    }
+
+    #[test]
+    fn test_parse_update_escaped_commas() {
+        let result = parse_update_fields("name:Eggs\\, milk, description:C:\\Temp\\\\").unwrap();
+
+        assert_eq!(result.name, Some("Eggs, milk".to_string()));
+        assert_eq!(result.description, Some("C:\\Temp\\".to_string()));
+
+        let value = "a, b \\, c\\";
+        let result = parse_update_fields(&format!("name:{}", escape_value(value))).unwrap();
+        assert_eq!(result.name, Some(value.to_string()));
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("  a b  a "), vec!["a".to_string(), "b".to_string()]);
+        assert!(parse_tags("").is_empty());
+    }
+
     #[test]
     fn test_parse_update_invalid_due_date(){
         let result = parse_update_fields("due_date:invalid");
@@ -0,0 +1,258 @@
+use std::{fs, path::PathBuf};
+
+use crate::paths;
+
+use super::{backup::Retention, persistence::{self, StoreFormat}, store::{self, TaskStore}, Tasks, TaskError};
+
+/// The workspace stored in the configured data file.
+pub const DEFAULT: &str = "default";
+
+// Remembers `tasks workspace use` between runs, next to the data file
+const CURRENT_FILE: &str = "workspace";
+const DIR: &str = "workspaces";
+
+/// Named task lists, each with its own store. `default` is the configured
+/// data file, the others live in `workspaces/<name>.<ext>` next to it and use
+/// the same storage format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspaces {
+    pub default_file: PathBuf,
+    pub format: Option<StoreFormat>,
+    pub retention: Retention,
+}
+
+impl Default for Workspaces {
+    fn default() -> Self {
+        Workspaces::new(paths::default_data_file(), None, Retention::default())
+    }
+}
+
+impl Workspaces {
+    pub fn new(default_file: impl Into<PathBuf>, format: Option<StoreFormat>, retention: Retention) -> Workspaces {
+        Workspaces { default_file: default_file.into(), format, retention }
+    }
+
+    /// The directory holding the default file and `workspaces/`, which is
+    /// also where the git repository for all of them lives.
+    pub fn root(&self) -> PathBuf {
+        match self.default_file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    fn extension(&self) -> String {
+        match self.default_file.extension() {
+            Some(extension) => extension.to_string_lossy().to_string(),
+            None => self.format.unwrap_or_default().extension().to_string(),
+        }
+    }
+
+    /// The data file of workspace `name`.
+    pub fn path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT {
+            self.default_file.clone()
+        } else {
+            self.root().join(DIR).join(format!("{}.{}", name, self.extension()))
+        }
+    }
+
+    pub fn open(&self, name: &str) -> Box<dyn TaskStore> {
+        store::open(Some(&self.path(name)), self.format, self.retention)
+    }
+
+    /// False for names `create` would refuse, so `..` can't reach files
+    /// outside `workspaces/`.
+    pub fn exists(&self, name: &str) -> bool {
+        name == DEFAULT || (check_name(name).is_ok() && self.path(name).exists())
+    }
+
+    /// Every workspace, `default` first and the rest by name.
+    pub fn list(&self) -> Result<Vec<String>, TaskError> {
+        let mut names = Vec::new();
+        let entries = match fs::read_dir(self.root().join(DIR)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![DEFAULT.to_string()]),
+            Err(err) => return Err(err.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            // Skips locks, snapshots and the like
+            if path.extension().is_some_and(|e| e.to_string_lossy() == self.extension()) {
+                names.extend(path.file_stem().map(|s| s.to_string_lossy().to_string()).filter(|name| check_name(name).is_ok()));
+            }
+        }
+        names.sort();
+        names.insert(0, DEFAULT.to_string());
+        Ok(names)
+    }
+
+    /// The workspace chosen with `tasks workspace use`, `default` if none was
+    /// or it has since been deleted.
+    pub fn current(&self) -> String {
+        fs::read_to_string(self.root().join(CURRENT_FILE))
+            .map(|name| name.trim().to_string())
+            .ok()
+            .filter(|name| !name.is_empty() && self.exists(name))
+            .unwrap_or_else(|| DEFAULT.to_string())
+    }
+
+    pub fn switch(&self, name: &str) -> Result<(), TaskError> {
+        self.check_exists(name)?;
+        fs::create_dir_all(self.root())?;
+        fs::write(self.root().join(CURRENT_FILE), format!("{}\n", name))?;
+        Ok(())
+    }
+
+    /// Creates an empty workspace.
+    pub fn create(&self, name: &str) -> Result<PathBuf, TaskError> {
+        check_name(name)?;
+        if self.exists(name) {
+            return Err(TaskError::InvalidInput(format!("workspace {} already exists", name)));
+        }
+        let path = self.path(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.open(name).save(&Tasks::new())?;
+        Ok(path)
+    }
+
+    /// Deletes a workspace and its tasks. `default` and the current workspace
+    /// can't be deleted, nor one with tasks unless `force` is set.
+    pub fn delete(&self, name: &str, force: bool) -> Result<usize, TaskError> {
+        self.check_exists(name)?;
+        if name == DEFAULT || name == self.current() {
+            return Err(TaskError::InvalidInput(format!("workspace {} is in use and can't be deleted", name)));
+        }
+        let count = match self.open(name).load() {
+            Ok(tasks) => tasks.tasks.len(),
+            Err(TaskError::NoFile) => 0,
+            Err(err) => return Err(err),
+        };
+        if count > 0 && !force {
+            return Err(TaskError::InvalidInput(format!("workspace {} still has {} tasks, use --force to delete them", name, count)));
+        }
+
+        let path = self.path(name);
+        fs::remove_file(&path)?;
+        for suffix in [".lock", ".snapshot"] {
+            let _ = fs::remove_file(persistence::sibling(&path, suffix));
+        }
+        Ok(count)
+    }
+
+    /// Loads every workspace, `default` first.
+    pub fn load_all(&self) -> Result<Vec<(String, Tasks)>, TaskError> {
+        self.list()?
+            .into_iter()
+            .map(|name| match self.open(&name).load() {
+                Ok(tasks) => Ok((name, tasks)),
+                Err(TaskError::NoFile) => Ok((name, Tasks::new())),
+                Err(err) => Err(err),
+            })
+            .collect()
+    }
+
+    pub fn check_exists(&self, name: &str) -> Result<(), TaskError> {
+        check_name(name)?;
+        if self.exists(name) {
+            Ok(())
+        } else {
+            Err(TaskError::InvalidInput(format!("no workspace called {}, see `tasks workspace list`", name)))
+        }
+    }
+}
+
+// Names end up in file names
+fn check_name(name: &str) -> Result<(), TaskError> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(TaskError::InvalidInput(format!("workspace names may only use letters, digits, - and _, not {:?}", name)))
+    }
+}
+
+/// Moves task `id` from `from` into `to`, where it gets the next free id.
+/// Returns the new id.
+pub fn move_task(from: &mut Tasks, to: &mut Tasks, id: u32) -> Result<u32, TaskError> {
+    let index = from.tasks.iter().position(|t| t.id == id).ok_or(TaskError::NotFound)?;
+    let mut task = from.tasks.remove(index);
+    task.id = to.next_id;
+    to.next_id += 1;
+    to.tasks.push(task);
+    Ok(to.next_id - 1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn workspaces(name: &str) -> Workspaces {
+        let dir = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Workspaces::new(dir.join("tasks.yaml"), None, Retention::none())
+    }
+
+    #[test]
+    fn creates_switches_and_deletes() {
+        let workspaces = workspaces("workspace_creates_switches_and_deletes");
+        assert_eq!(workspaces.list().unwrap(), vec!["default"]);
+        assert_eq!(workspaces.current(), "default");
+
+        let path = workspaces.create("work").unwrap();
+        workspaces.create("home").unwrap();
+        assert!(path.ends_with("workspaces/work.yaml"));
+        assert!(workspaces.create("work").is_err());
+        assert!(workspaces.create("../etc").is_err());
+        assert_eq!(workspaces.list().unwrap(), vec!["default", "home", "work"]);
+
+        workspaces.switch("work").unwrap();
+        assert_eq!(workspaces.current(), "work");
+        assert!(workspaces.switch("oss").is_err());
+        assert!(workspaces.delete("work", false).is_err());
+
+        let mut home = workspaces.open("home").load().unwrap();
+        home.add_task("Laundry".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        workspaces.open("home").save(&home).unwrap();
+        assert!(workspaces.delete("home", false).is_err());
+        assert_eq!(workspaces.delete("home", true).unwrap(), 1);
+        assert_eq!(workspaces.list().unwrap(), vec!["default", "work"]);
+    }
+
+    #[test]
+    fn rejects_names_outside_the_workspaces_dir() {
+        let workspaces = workspaces("workspace_rejects_names_outside");
+        workspaces.create("work").unwrap();
+        workspaces.open(DEFAULT).save(&Tasks::new()).unwrap();
+
+        // `workspaces/../tasks.yaml` is the default workspace's file
+        assert!(!workspaces.exists("../tasks"));
+        assert!(workspaces.check_exists("../tasks").is_err());
+        assert!(workspaces.delete("../tasks", true).is_err());
+        assert!(workspaces.switch("../tasks").is_err());
+        assert!(workspaces.default_file.exists());
+    }
+
+    #[test]
+    fn moves_tasks_with_a_new_id() {
+        let mut from = Tasks::new();
+        from.add_task("A".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        from.add_task("B".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+        let mut to = Tasks::new();
+        to.add_task("C".to_string(), "".to_string(), "2024-05-01T09:00:00Z".to_string());
+
+        assert_eq!(move_task(&mut from, &mut to, 2).unwrap(), 2);
+        assert_eq!(move_task(&mut from, &mut to, 1).unwrap(), 3);
+        assert!(matches!(move_task(&mut from, &mut to, 1), Err(TaskError::NotFound)));
+
+        assert!(from.tasks.is_empty());
+        let names: Vec<(u32, &str)> = to.tasks.iter().map(|t| (t.id, t.name.as_str())).collect();
+        assert_eq!(names, vec![(1, "C"), (2, "B"), (3, "A")]);
+    }
+}
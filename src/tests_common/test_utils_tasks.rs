@@ -1,4 +1,4 @@
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 
 use crate::tasks::{Task, Tasks};
 
@@ -33,3 +33,62 @@ pub fn create_tasks_completion() -> Tasks {
 
     tasks
 } 
+
+/// An open task due at `due`, for tests to adjust with `TaskFixture`.
+pub fn task(id: u32, name: &str, due: &str) -> Task {
+    Task::new(id, name.to_string(), "".to_string(), due.to_string())
+}
+
+/// Task `id`, completed at `at`.
+pub fn done(id: u32, at: &str) -> Task {
+    task(id, &format!("Task {}", id), "2024-05-01T09:00:00Z").done_at(at)
+}
+
+pub fn day(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+}
+
+/// `list` with the next id after the highest one in it.
+pub fn tasks(list: Vec<Task>) -> Tasks {
+    let next_id = list.iter().map(|t| t.id + 1).max().unwrap_or(1);
+    Tasks { tasks: list, next_id }
+}
+
+/// Chained tweaks to a fixture task, e.g.
+/// `task(1, "Report", "2024-05-02T09:30:00Z").done_at("2024-05-01T10:00:00Z")`.
+pub trait TaskFixture {
+    /// Completed, without a completion time.
+    fn done(self) -> Task;
+    fn done_at(self, at: &str) -> Task;
+    fn created(self, at: &str) -> Task;
+    fn in_project(self, project: &str) -> Task;
+    fn tagged(self, tags: &[&str]) -> Task;
+}
+
+impl TaskFixture for Task {
+    fn done(mut self) -> Task {
+        self.completed = true;
+        self
+    }
+
+    fn done_at(mut self, at: &str) -> Task {
+        self.completed = true;
+        self.completed_at = Some(at.parse().unwrap());
+        self
+    }
+
+    fn created(mut self, at: &str) -> Task {
+        self.created_at = Some(at.parse().unwrap());
+        self
+    }
+
+    fn in_project(mut self, project: &str) -> Task {
+        self.project = Some(project.to_string());
+        self
+    }
+
+    fn tagged(mut self, tags: &[&str]) -> Task {
+        self.tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
+}